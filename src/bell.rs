@@ -0,0 +1,49 @@
+use crate::Error;
+use crate::ewmh::intern_atom;
+use xcb::Connection;
+
+/// The keyboard bell, rung through XKB so that it can be named for sound themes, or through the
+/// core protocol when the server lacks XKB.
+pub struct Bell {
+    xkb: bool,
+    window: xcb::Window,
+    percent: i8,
+    name: xcb::Atom,
+}
+
+impl Bell {
+    /// Prepare a bell at `percent` of the base volume, from -100 to 100, optionally named so that
+    /// listeners for XKB bell events can tell it apart.
+    pub fn new(conn: &Connection, window: xcb::Window, percent: i8, name: Option<&str>)
+        -> Result<Self, Error>
+    {
+        let xkb = matches!(xcb::xkb::use_extension(conn, 1, 0).get_reply(),
+            Ok(reply) if reply.supported());
+        if !xkb && name.is_some() {
+            eprintln!("Warning: the XKB extension is not supported, so the bell can't be named");
+        }
+
+        Ok(Self {
+            xkb,
+            window,
+            percent: percent.clamp(-100, 100),
+            name: match name {
+                Some(name) => intern_atom(conn, name)?,
+                None => xcb::ATOM_NONE,
+            },
+        })
+    }
+
+    pub fn ring(&self, conn: &Connection) {
+        if self.xkb {
+            // The default pitch and duration of the core keyboard's default bell
+            xcb::xkb::bell(conn, xcb::xkb::ID_USE_CORE_KBD as xcb::xkb::DeviceSpec,
+                xcb::xkb::ID_DFLT_XI_CLASS as xcb::xkb::BellClassSpec,
+                xcb::xkb::ID_DFLT_XI_ID as xcb::xkb::IdSpec, self.percent, false, false, 0, 0,
+                self.name, self.window);
+        } else {
+            xcb::bell(conn, self.percent);
+        }
+        conn.flush();
+    }
+}
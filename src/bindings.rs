@@ -0,0 +1,140 @@
+use crate::Error;
+use std::str::FromStr;
+
+/// What a mouse button does on the notification window.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Close the notification
+    Dismiss,
+    /// Drag the window around, cancelling the timeout
+    Move,
+    /// Cancel the timeout, keeping the notification on the screen
+    Pin,
+    /// Copy the message to the clipboard and primary selection
+    Copy,
+    ScrollUp,
+    ScrollDown,
+    PrevPage,
+    NextPage,
+    /// Run a shell command, with the message in `$XNOTIFY_TEXT`
+    Exec(String),
+    /// Do nothing, for removing a default binding
+    Nothing,
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        if let Some(command) = s.strip_prefix("exec:") {
+            return Ok(Action::Exec(command.to_owned()));
+        }
+
+        Ok(match s {
+            "dismiss" => Action::Dismiss,
+            "move" => Action::Move,
+            "pin" => Action::Pin,
+            "copy" => Action::Copy,
+            "scroll-up" => Action::ScrollUp,
+            "scroll-down" => Action::ScrollDown,
+            "prev-page" => Action::PrevPage,
+            "next-page" => Action::NextPage,
+            "none" => Action::Nothing,
+            _ => return Err(Error::InvalidBinding(s.to_owned())),
+        })
+    }
+}
+
+// Modifiers that take part in matching a binding. Lock and NumLock are ignored.
+const MODIFIERS: &[(&str, u16)] = &[
+    ("shift", xcb::MOD_MASK_SHIFT as u16),
+    ("ctrl", xcb::MOD_MASK_CONTROL as u16),
+    ("alt", xcb::MOD_MASK_1 as u16),
+    ("super", xcb::MOD_MASK_4 as u16),
+];
+
+fn relevant_modifiers(state: u16) -> u16 {
+    MODIFIERS.iter().fold(0, |mask, (_, m)| mask | (state & m))
+}
+
+/// The mask of a modifier name such as `ctrl`, as used in bindings.
+pub fn modifier_mask(name: &str) -> Option<u16> {
+    MODIFIERS.iter().find(|(n, _)| *n == name).map(|(_, mask)| *mask)
+}
+
+/// A mouse button, along with held modifiers, and the action it triggers.
+///
+/// Parsed from strings such as `button3=dismiss` or `ctrl+button1=exec:xdg-open "$XNOTIFY_TEXT"`.
+#[derive(Clone, Debug)]
+pub struct Binding {
+    button: u8,
+    modifiers: u16,
+    action: Action,
+}
+
+impl FromStr for Binding {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidBinding(s.to_owned());
+        let (keys, action) = s.split_once('=').ok_or_else(invalid)?;
+
+        let mut button = None;
+        let mut modifiers = 0;
+        for key in keys.split('+') {
+            if let Some(number) = key.strip_prefix("button") {
+                button = Some(number.parse().map_err(|_| invalid())?);
+            } else {
+                modifiers |= modifier_mask(key).ok_or_else(invalid)?;
+            }
+        }
+
+        Ok(Binding {
+            button: button.ok_or_else(invalid)?,
+            modifiers,
+            action: action.parse().map_err(|_| invalid())?,
+        })
+    }
+}
+
+/// The table of all mouse bindings, the defaults overridden by the user's.
+pub struct Bindings {
+    bindings: Vec<Binding>,
+}
+
+impl Bindings {
+    pub fn new(user: &[Binding]) -> Self {
+        let defaults = [
+            (xcb::BUTTON_INDEX_1, Action::Move),
+            (xcb::BUTTON_INDEX_2, Action::Copy),
+            (xcb::BUTTON_INDEX_3, Action::Dismiss),
+            (xcb::BUTTON_INDEX_4, Action::ScrollUp),
+            (xcb::BUTTON_INDEX_5, Action::ScrollDown),
+        ];
+
+        let mut bindings: Vec<Binding> = defaults.iter()
+            .map(|(button, action)| Binding {
+                button: *button as u8,
+                modifiers: 0,
+                action: action.clone(),
+            })
+            .collect();
+
+        for binding in user {
+            bindings.retain(|b| b.button != binding.button || b.modifiers != binding.modifiers);
+            bindings.push(binding.clone());
+        }
+
+        Self { bindings }
+    }
+
+    /// The action for a press of `button`, with the modifier `state` of the event.
+    pub fn action(&self, button: u8, state: u16) -> &Action {
+        let modifiers = relevant_modifiers(state);
+
+        self.bindings.iter()
+            .find(|b| b.button == button && b.modifiers == modifiers)
+            .map(|b| &b.action)
+            .unwrap_or(&Action::Nothing)
+    }
+}
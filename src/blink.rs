@@ -0,0 +1,161 @@
+use crate::Error;
+use crate::leechbar::util::Color;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How the colors change while the window blinks.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Pattern {
+    /// Flip between the normal and the blink colors
+    Square,
+    /// Fade smoothly between the normal and the blink colors
+    Pulse,
+    /// Flip only the border's color
+    Border,
+    /// Two short flashes followed by a pause
+    Heartbeat,
+}
+
+impl FromStr for Pattern {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(match s {
+            "square" => Pattern::Square,
+            "pulse" => Pattern::Pulse,
+            "border" => Pattern::Border,
+            "heartbeat" => Pattern::Heartbeat,
+            _ => return Err(Error::InvalidBlinkPattern(s.to_owned())),
+        })
+    }
+}
+
+/// Parse a time in seconds, such as `0.25`, which can't be negative.
+pub fn parse_secs(s: &str) -> Result<Duration, Error> {
+    s.parse::<f32>().ok()
+        .and_then(|secs| Duration::try_from_secs_f32(secs).ok())
+        .ok_or_else(|| Error::InvalidDuration(s.to_owned()))
+}
+
+/// The colors of the parts of the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colors {
+    pub background: Color,
+    pub text: Color,
+    pub border: Color,
+}
+
+/// What to paint the window with at one moment of the blink.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Paint {
+    pub background: Color,
+    pub border: Color,
+    /// The countdown and scroll bars, which stand out from the background
+    pub bars: Color,
+    /// How far the text is from the normal (0) to the blink (1) color
+    pub text: f64,
+}
+
+fn mix(from: Color, to: Color, level: f64) -> Color {
+    let channel = |from: u8, to: u8| (from as f64 + (to as f64 - from as f64) * level).round() as u8;
+    Color::new(channel(from.red, to.red), channel(from.green, to.green),
+        channel(from.blue, to.blue), channel(from.alpha, to.alpha))
+}
+
+fn invert(color: Color) -> Color {
+    Color::new(255 - color.red, 255 - color.green, 255 - color.blue, color.alpha)
+}
+
+/// Blinking of the window for `duration` after it appears, changing every `rate`.
+pub struct Blink {
+    pub pattern: Pattern,
+    pub rate: Duration,
+    pub duration: Duration,
+    pub normal: Colors,
+    pub blink: Colors,
+}
+
+impl Blink {
+    /// How far the colors are from the normal (0) to the blink (1) ones, `elapsed` after the
+    /// window appeared. The level is rounded to steps of 1/64, so that fading redraws at a
+    /// limited rate.
+    pub fn level(&self, elapsed: Duration) -> f64 {
+        if elapsed > self.duration || self.rate.as_secs_f64() <= 0. {
+            return 0.;
+        }
+
+        let phase = elapsed.as_secs_f64() / self.rate.as_secs_f64();
+        let level = match self.pattern {
+            Pattern::Square | Pattern::Border => match phase as u64 % 2 {
+                0 => 1.,
+                _ => 0.,
+            },
+            Pattern::Pulse => (1. + (std::f64::consts::PI * phase).cos()) / 2.,
+            // Flashes in the first and third of eight steps
+            Pattern::Heartbeat => match phase as u64 % 8 {
+                0 | 2 => 1.,
+                _ => 0.,
+            },
+        };
+
+        (level * 64.).round() / 64.
+    }
+
+    /// What to paint at the blink `level`.
+    pub fn paint(&self, level: f64) -> Paint {
+        let (normal, blink) = (self.normal, self.blink);
+
+        if self.pattern == Pattern::Border {
+            // Flashing the border in its own color would not show, so use the inverse instead
+            let border = if blink.border == normal.border {
+                invert(normal.border)
+            } else {
+                blink.border
+            };
+            return Paint {
+                background: normal.background,
+                border: mix(normal.border, border, level),
+                bars: normal.border,
+                text: 0.,
+            };
+        }
+
+        Paint {
+            background: mix(normal.background, blink.background, level),
+            border: mix(normal.border, blink.border, level),
+            bars: mix(normal.border, normal.background, level),
+            text: level,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Color = Color { red: 255, green: 255, blue: 255, alpha: 255 };
+    const BLACK: Color = Color { red: 0, green: 0, blue: 0, alpha: 255 };
+    const RED: Color = Color { red: 255, green: 0, blue: 0, alpha: 255 };
+
+    fn border_blink(blink_border: Color) -> Blink {
+        let normal = Colors { background: BLACK, text: WHITE, border: WHITE };
+        Blink {
+            pattern: Pattern::Border,
+            rate: Duration::from_millis(50),
+            duration: Duration::from_millis(250),
+            normal,
+            blink: Colors { border: blink_border, ..normal },
+        }
+    }
+
+    #[test]
+    fn border_flashes_blink_color() {
+        assert_eq!(border_blink(RED).paint(1.).border, RED);
+        assert_eq!(border_blink(RED).paint(0.).border, WHITE);
+    }
+
+    #[test]
+    fn border_flashes_inverse_of_same_color() {
+        assert_eq!(border_blink(WHITE).paint(1.).border, BLACK);
+    }
+}
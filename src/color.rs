@@ -0,0 +1,35 @@
+use crate::Error;
+use crate::leechbar::util::Color;
+
+// Colors that can be given by name
+const NAMES: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0, 0, 0)),
+    ("white", (255, 255, 255)),
+    ("gray", (128, 128, 128)),
+    ("red", (255, 0, 0)),
+    ("green", (0, 255, 0)),
+    ("blue", (0, 0, 255)),
+    ("yellow", (255, 255, 0)),
+    ("cyan", (0, 255, 255)),
+    ("magenta", (255, 0, 255)),
+    ("orange", (255, 165, 0)),
+];
+
+/// Parse a color given as `#rrggbb`, `#rrggbbaa` or by name, such as `black`.
+pub fn parse(s: &str) -> Result<Color, Error> {
+    let invalid = || Error::InvalidColor(s.to_owned());
+
+    if let Some((_, (red, green, blue))) = NAMES.iter().find(|(name, _)| *name == s) {
+        return Ok(Color::new(*red, *green, *blue, 255));
+    }
+
+    let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+
+    Ok(Color::new(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
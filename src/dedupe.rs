@@ -0,0 +1,117 @@
+use crate::Error;
+use crate::ewmh::intern_atom;
+use crate::history;
+use xcb::Connection;
+
+/// Merging of repeated notifications into the window that is already shown.
+///
+/// Each window is marked with a key, its tag or text, and when it was last shown. Another
+/// invocation with the same key sends the window a client message instead of showing its own.
+pub struct Dedupe {
+    key: xcb::Atom,
+    shown: xcb::Atom,
+    repeat: xcb::Atom,
+    utf8_string: xcb::Atom,
+}
+
+/// The key that identifies repeats of a notification, its tag if it has one.
+pub fn key(tag: &str, text: &str) -> String {
+    if tag.is_empty() {
+        format!("text:{}", text)
+    } else {
+        format!("tag:{}", tag)
+    }
+}
+
+impl Dedupe {
+    pub fn new(conn: &Connection) -> Result<Self, Error> {
+        Ok(Self {
+            key: intern_atom(conn, "_XNOTIFY_KEY")?,
+            shown: intern_atom(conn, "_XNOTIFY_SHOWN")?,
+            repeat: intern_atom(conn, "_XNOTIFY_REPEAT")?,
+            utf8_string: intern_atom(conn, "UTF8_STRING")?,
+        })
+    }
+
+    /// Mark `win` as showing the notification with `key`.
+    pub fn mark(&self, conn: &Connection, win: xcb::Window, key: &str) {
+        xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, win, self.key, self.utf8_string,
+            8, key.as_bytes());
+        self.touch(conn, win);
+    }
+
+    /// Record that the notification of `win` was shown again just now.
+    pub fn touch(&self, conn: &Connection, win: xcb::Window) {
+        xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, win, self.shown,
+            xcb::ATOM_CARDINAL, 32, &[history::now() as u32]);
+    }
+
+    // Whether `win` shows the notification with `key`, and was shown within `max_age` seconds.
+    // Windows can be destroyed meanwhile, which counts as not.
+    fn is_recent(&self, conn: &Connection, win: xcb::Window, key: &str, max_age: u64) -> bool {
+        let marked = xcb::get_property(conn, false, win, self.key, self.utf8_string, 0,
+            key.len() as u32 / 4 + 1).get_reply();
+        if !matches!(&marked, Ok(marked) if marked.value::<u8>() == key.as_bytes()) {
+            return false;
+        }
+
+        match xcb::get_property(conn, false, win, self.shown, xcb::ATOM_CARDINAL, 0, 1).get_reply() {
+            Ok(shown) => match shown.value::<u32>() {
+                [shown] => history::now().saturating_sub(*shown as u64) <= max_age,
+                _ => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Find a visible window showing the notification with `key` within `max_age` seconds.
+    ///
+    /// Windows managed with --managed are looked for in the window manager's frames too.
+    pub fn find(&self, conn: &Connection, root: xcb::Window, key: &str, max_age: u64)
+        -> Result<Option<xcb::Window>, Error>
+    {
+        let tree = xcb::query_tree(conn, root).get_reply()?;
+
+        for child in tree.children() {
+            let attrs = match xcb::get_window_attributes(conn, *child).get_reply() {
+                Ok(attrs) => attrs,
+                Err(_) => continue,
+            };
+            if attrs.map_state() != xcb::MAP_STATE_VIEWABLE as u8 {
+                continue;
+            }
+
+            if self.is_recent(conn, *child, key, max_age) {
+                return Ok(Some(*child));
+            }
+            if let Ok(frame) = xcb::query_tree(conn, *child).get_reply() {
+                for grandchild in frame.children() {
+                    if self.is_recent(conn, *grandchild, key, max_age) {
+                        return Ok(Some(*grandchild));
+                    }
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Tell the notification of `win` that it was repeated.
+    pub fn send_repeat(&self, conn: &Connection, win: xcb::Window) {
+        let data = xcb::ClientMessageData::from_data32([0; 5]);
+        let event = xcb::ClientMessageEvent::new(32, win, self.repeat, data);
+
+        // Without an event mask, the event goes to the client that created the window
+        xcb::send_event(conn, false, win, 0, &event);
+        conn.flush();
+    }
+
+    /// Whether `event` tells that the notification was repeated.
+    pub fn is_repeat(&self, event: &xcb::GenericEvent) -> bool {
+        if event.response_type() & !0x80 != xcb::CLIENT_MESSAGE {
+            return false;
+        }
+        let event: &xcb::ClientMessageEvent = unsafe { xcb::cast_event(event) };
+        event.type_() == self.repeat
+    }
+}
@@ -0,0 +1,190 @@
+use crate::Error;
+use crate::ewmh::intern_atom;
+use crate::history::{self, Urgency};
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use xcb::Connection;
+
+// The root window property that holds the settings while do not disturb is on
+const PROPERTY: &str = "_XNOTIFY_DND";
+
+/// What happens to a notification while do not disturb is on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Action {
+    Show,
+    /// Keep it on disk, to show it when do not disturb is turned off
+    Queue,
+    Suppress,
+}
+
+impl FromStr for Action {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(match s {
+            "show" => Action::Show,
+            "queue" => Action::Queue,
+            "suppress" => Action::Suppress,
+            _ => return Err(Error::InvalidDndAction(s.to_owned())),
+        })
+    }
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Show => "show",
+            Action::Queue => "queue",
+            Action::Suppress => "suppress",
+        }
+    }
+
+    fn from_u32(n: u32) -> Self {
+        match n {
+            0 => Action::Show,
+            1 => Action::Queue,
+            _ => Action::Suppress,
+        }
+    }
+
+    fn to_u32(self) -> u32 {
+        match self {
+            Action::Show => 0,
+            Action::Queue => 1,
+            Action::Suppress => 2,
+        }
+    }
+}
+
+/// The actions for each urgency while do not disturb is on.
+#[derive(Clone, Copy, Debug)]
+pub struct Settings {
+    pub low: Action,
+    pub normal: Action,
+    pub critical: Action,
+}
+
+impl Settings {
+    pub fn action(&self, urgency: Urgency) -> Action {
+        match urgency {
+            Urgency::Low => self.low,
+            Urgency::Normal => self.normal,
+            Urgency::Critical => self.critical,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        format!("low: {}, normal: {}, critical: {}", self.low.as_str(), self.normal.as_str(),
+            self.critical.as_str())
+    }
+}
+
+/// The settings of do not disturb, if it is on for the screen of `root`.
+pub fn get(conn: &Connection, root: xcb::Window) -> Result<Option<Settings>, Error> {
+    let property = intern_atom(conn, PROPERTY)?;
+    let reply = xcb::get_property(conn, false, root, property, xcb::ATOM_CARDINAL, 0, 3)
+        .get_reply()?;
+
+    Ok(match reply.value::<u32>() {
+        [low, normal, critical] => Some(Settings {
+            low: Action::from_u32(*low),
+            normal: Action::from_u32(*normal),
+            critical: Action::from_u32(*critical),
+        }),
+        _ => None,
+    })
+}
+
+/// Turn do not disturb on with `settings`, or off with `None`.
+pub fn set(conn: &Connection, root: xcb::Window, settings: Option<&Settings>) -> Result<(), Error> {
+    let property = intern_atom(conn, PROPERTY)?;
+
+    match settings {
+        Some(settings) => {
+            let value = [settings.low.to_u32(), settings.normal.to_u32(),
+                settings.critical.to_u32()];
+            xcb::change_property_checked(conn, xcb::PROP_MODE_REPLACE as u8, root, property,
+                xcb::ATOM_CARDINAL, 32, &value).request_check()?;
+        },
+        None => xcb::delete_property_checked(conn, root, property).request_check()?,
+    }
+
+    Ok(())
+}
+
+/// A notification that waits for do not disturb to be turned off.
+#[derive(Serialize, Deserialize)]
+pub struct Queued {
+    pub text: String,
+    pub queued: u64,
+    /// The command line arguments of the invocation
+    pub args: Vec<String>,
+}
+
+fn queue_path() -> Option<PathBuf> {
+    Some(state::dir()?.join("queue.jsonl"))
+}
+
+/// Add a notification to the end of the queue.
+pub fn enqueue(text: &str, args: &[String]) -> Result<(), Error> {
+    append(&Queued { text: text.to_owned(), queued: history::now(), args: args.to_vec() })
+}
+
+fn append(queued: &Queued) -> Result<(), Error> {
+    let path = match queue_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    std::fs::create_dir_all(path.parent().unwrap())?;
+
+    let line = serde_json::to_string(queued)? + "\n";
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all(line.as_bytes())?;
+
+    Ok(())
+}
+
+/// Empty the queue, handing the notifications that were in it to `show`, oldest first. Those
+/// that `show` fails for are reported and queued again.
+pub fn replay_queue(mut show: impl FnMut(&Queued) -> Result<(), Error>) -> Result<(), Error> {
+    let path = match queue_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    // Move the queue away first, so that nothing queued meanwhile gets lost
+    let taken = path.with_extension(format!("taken.{}", std::process::id()));
+    match std::fs::rename(&path, &taken) {
+        Ok(()) => {},
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    }
+    let content = std::fs::read_to_string(&taken)?;
+
+    for queued in state::parse_json_lines::<Queued>(&path, &content) {
+        if let Err(e) = show(&queued) {
+            eprintln!("Could not show a queued notification, keeping it queued; {}", e);
+            append(&queued)?;
+        }
+    }
+
+    // Only now, so that the notifications are still on disk if queueing one again failed
+    std::fs::remove_file(&taken)?;
+    Ok(())
+}
+
+/// The notifications in the queue, oldest first.
+pub fn queue() -> Vec<Queued> {
+    let path = match queue_path() {
+        Some(path) => path,
+        None => return vec![],
+    };
+
+    match std::fs::read_to_string(&path) {
+        Ok(content) => state::parse_json_lines(&path, &content),
+        Err(_) => vec![],
+    }
+}
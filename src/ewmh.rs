@@ -0,0 +1,71 @@
+use crate::Error;
+use xcb::Connection;
+
+pub fn intern_atom(conn: &Connection, name: &str) -> Result<xcb::Atom, Error> {
+    Ok(xcb::intern_atom(conn, false, name).get_reply()?.atom())
+}
+
+fn set_atoms(conn: &Connection, win: xcb::Window, property: &str, values: &[&str]) -> Result<(), Error> {
+    let property = intern_atom(conn, property)?;
+    let mut atoms = vec![];
+    for value in values {
+        atoms.push(intern_atom(conn, value)?);
+    }
+
+    xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, win, property, xcb::ATOM_ATOM, 32,
+        &atoms);
+    Ok(())
+}
+
+/// Describe the window to the window manager as a notification, for windows that are not
+/// override-redirect.
+///
+/// The window is kept above others and out of taskbars and pagers. It does not take focus, and
+/// it asks to be placed at `pos` without decorations.
+pub fn set_notification_hints(conn: &Connection, win: xcb::Window, pos: (i16, i16),
+    size: (u16, u16)) -> Result<(), Error>
+{
+    set_atoms(conn, win, "_NET_WM_WINDOW_TYPE", &["_NET_WM_WINDOW_TYPE_NOTIFICATION"])?;
+    set_atoms(conn, win, "_NET_WM_STATE", &[
+        "_NET_WM_STATE_ABOVE",
+        "_NET_WM_STATE_STICKY",
+        "_NET_WM_STATE_SKIP_TASKBAR",
+        "_NET_WM_STATE_SKIP_PAGER",
+    ])?;
+
+    let mode = xcb::PROP_MODE_REPLACE as u8;
+    xcb::change_property(conn, mode, win, xcb::ATOM_WM_CLASS, xcb::ATOM_STRING, 8,
+        b"xnotify\0Xnotify\0");
+    xcb::change_property(conn, mode, win, xcb::ATOM_WM_NAME, xcb::ATOM_STRING, 8, b"xnotify");
+    xcb::change_property(conn, mode, win, intern_atom(conn, "_NET_WM_PID")?, xcb::ATOM_CARDINAL,
+        32, &[std::process::id()]);
+
+    // A user time of zero asks the window manager not to focus the window when mapped
+    xcb::change_property(conn, mode, win, intern_atom(conn, "_NET_WM_USER_TIME")?,
+        xcb::ATOM_CARDINAL, 32, &[0u32]);
+
+    // WM_HINTS with the InputHint flag and input=false
+    let wm_hints: [u32; 9] = [1, 0, 0, 0, 0, 0, 0, 0, 0];
+    xcb::change_property(conn, mode, win, xcb::ATOM_WM_HINTS, xcb::ATOM_WM_HINTS, 32, &wm_hints);
+
+    // WM_NORMAL_HINTS with USPosition, PPosition and a fixed size
+    let (width, height) = (size.0 as u32, size.1 as u32);
+    let mut size_hints = [0u32; 18];
+    size_hints[0] = 1 | 4 | 16 | 32;
+    size_hints[1] = pos.0 as i32 as u32;
+    size_hints[2] = pos.1 as i32 as u32;
+    size_hints[3] = width;
+    size_hints[4] = height;
+    size_hints[5] = width;
+    size_hints[6] = height;
+    size_hints[7] = width;
+    size_hints[8] = height;
+    xcb::change_property(conn, mode, win, xcb::ATOM_WM_NORMAL_HINTS, xcb::ATOM_WM_SIZE_HINTS, 32,
+        &size_hints);
+
+    // _MOTIF_WM_HINTS with the decorations flag and no decorations
+    let motif = intern_atom(conn, "_MOTIF_WM_HINTS")?;
+    xcb::change_property(conn, mode, win, motif, motif, 32, &[2u32, 0, 0, 0, 0]);
+
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use crate::Error;
+use pango::{FontDescription, FontFamilyExt, FontMapExt};
+
+// Generic families that fontconfig always resolves to some installed font
+const ALIASES: &[&str] = &["sans", "sans-serif", "serif", "monospace", "mono", "system-ui",
+    "cursive", "fantasy", "emoji"];
+
+/// Names of the font families known to Pango, sorted.
+pub fn families() -> Vec<String> {
+    let mut families: Vec<String> = pangocairo::FontMap::get_default()
+        .map(|map| map.list_families())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|family| family.get_name())
+        .map(|name| name.to_string())
+        .collect();
+
+    families.sort_by_key(|name| name.to_lowercase());
+    families.dedup();
+    families
+}
+
+/// Check that one of the families of `font` is installed, as Pango silently falls back to
+/// another font otherwise. Errors in that case when `strict`, and only warns if not.
+pub fn check(font: &FontDescription, strict: bool) -> Result<(), Error> {
+    let requested = match font.get_family() {
+        Some(requested) => requested.to_string(),
+        None => return Ok(()),
+    };
+
+    let installed = families();
+    let resolves = requested.split(',').map(|name| name.trim().to_lowercase()).any(|name| {
+        ALIASES.contains(&name.as_str()) || installed.iter().any(|f| f.to_lowercase() == name)
+    });
+
+    if resolves {
+        Ok(())
+    } else if strict {
+        Err(Error::UnknownFont(requested))
+    } else {
+        eprintln!("Warning: font family '{}' not found, using a fallback. See --list-fonts",
+            requested);
+        Ok(())
+    }
+}
@@ -0,0 +1,165 @@
+use crate::Error;
+use crate::state;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+// The log is rotated to history.jsonl.1 once it grows past this size
+const MAX_SIZE: u64 = 1024 * 1024;
+
+/// How important a notification is.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl FromStr for Urgency {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        Ok(match s {
+            "low" => Urgency::Low,
+            "normal" => Urgency::Normal,
+            "critical" => Urgency::Critical,
+            _ => return Err(Error::InvalidUrgency(s.to_owned())),
+        })
+    }
+}
+
+impl Urgency {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Urgency::Low => "low",
+            Urgency::Normal => "normal",
+            Urgency::Critical => "critical",
+        }
+    }
+}
+
+/// Why a notification went away.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Reason {
+    Timeout,
+    /// A mouse button bound to dismiss
+    Click,
+    /// The key given to --dismiss-key
+    Key,
+    /// Another client destroyed the window
+    Destroy,
+}
+
+impl Reason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Reason::Timeout => "timeout",
+            Reason::Click => "click",
+            Reason::Key => "key",
+            Reason::Destroy => "destroy",
+        }
+    }
+}
+
+/// A notification that was shown, as logged to the history.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Record {
+    pub text: String,
+    pub tag: String,
+    pub urgency: Urgency,
+    /// When the notification appeared and went away, in seconds since the Unix epoch
+    pub shown: u64,
+    pub closed: u64,
+    pub reason: Reason,
+    /// Where the window was last
+    pub x: i16,
+    pub y: i16,
+    /// The command line arguments of the invocation
+    pub args: Vec<String>,
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn path() -> Option<PathBuf> {
+    Some(state::dir()?.join("history.jsonl"))
+}
+
+/// Append `record` to the history, rotating the log if it got too large.
+pub fn append(record: &Record) -> Result<(), Error> {
+    let path = match path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+    std::fs::create_dir_all(path.parent().unwrap())?;
+
+    if matches!(std::fs::metadata(&path), Ok(metadata) if metadata.len() > MAX_SIZE) {
+        std::fs::rename(&path, path.with_extension("jsonl.1"))?;
+    }
+
+    // Appending a single line is atomic, so concurrent notifications don't mix their records
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&path)?;
+    file.write_all((serde_json::to_string(record)? + "\n").as_bytes())?;
+
+    Ok(())
+}
+
+/// All records of the history, including the rotated log, oldest first.
+pub fn load() -> Vec<Record> {
+    let path = match path() {
+        Some(path) => path,
+        None => return vec![],
+    };
+
+    [path.with_extension("jsonl.1"), path].iter()
+        .flat_map(|path| match std::fs::read_to_string(path) {
+            Ok(content) => state::parse_json_lines(path, &content),
+            Err(_) => vec![],
+        })
+        .collect()
+}
+
+/// Parse an age such as `90`, `30s`, `15m`, `2h` or `7d` into seconds.
+pub fn parse_age(s: &str) -> Result<u64, Error> {
+    let invalid = || Error::InvalidAge(s.to_owned());
+    let (number, unit) = match s.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&s[..i], c),
+        _ => (s, 's'),
+    };
+
+    let unit = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    number.parse::<u64>().ok()
+        .and_then(|number| number.checked_mul(unit))
+        .ok_or_else(invalid)
+}
+
+/// Format seconds since the Unix epoch as a UTC date and time, `YYYY-MM-DD HH:MM:SS`.
+pub fn format_time(secs: u64) -> String {
+    let (days, time) = ((secs / 86400) as i64, secs % 86400);
+
+    // Convert days since the epoch to a civil date, after Howard Hinnant's algorithm
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02} {:02}:{:02}:{:02}", year, month, day, time / 3600,
+        time / 60 % 60, time % 60)
+}
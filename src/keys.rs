@@ -0,0 +1,104 @@
+use crate::Error;
+use crate::bindings::modifier_mask;
+use std::str::FromStr;
+use xcb::Connection;
+
+// Keysyms of named keys that are not a single character
+const NAMED_KEYSYMS: &[(&str, u32)] = &[
+    ("Escape", 0xff1b),
+    ("Return", 0xff0d),
+    ("Tab", 0xff09),
+    ("BackSpace", 0xff08),
+    ("Delete", 0xffff),
+    ("Insert", 0xff63),
+    ("Home", 0xff50),
+    ("End", 0xff57),
+    ("Pause", 0xff13),
+    ("space", 0x20),
+];
+
+fn keysym(name: &str) -> Option<u32> {
+    if let Some((_, keysym)) = NAMED_KEYSYMS.iter().find(|(n, _)| *n == name) {
+        return Some(*keysym);
+    }
+
+    // F1 to F35 are consecutive
+    if let Some(number) = name.strip_prefix('F') {
+        if let Ok(number @ 1..=35) = number.parse::<u32>() {
+            return Some(0xffbe + number - 1);
+        }
+    }
+
+    // Latin-1 characters have keysyms equal to their code points
+    let mut chars = name.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) if (c as u32) >= 0x20 && (c as u32) < 0x100 => {
+            Some(c.to_ascii_lowercase() as u32)
+        },
+        _ => None,
+    }
+}
+
+/// A key combination on the keyboard, such as `ctrl+alt+Escape`.
+#[derive(Clone, Debug)]
+pub struct Hotkey {
+    name: String,
+    modifiers: u16,
+    keysym: u32,
+}
+
+impl FromStr for Hotkey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidHotkey(s.to_owned());
+        let mut parts: Vec<&str> = s.split('+').collect();
+        let key = parts.pop().ok_or_else(invalid)?;
+
+        let mut modifiers = 0;
+        for part in parts {
+            modifiers |= modifier_mask(part).ok_or_else(invalid)?;
+        }
+
+        Ok(Hotkey {
+            name: s.to_owned(),
+            modifiers,
+            keysym: keysym(key).ok_or_else(invalid)?,
+        })
+    }
+}
+
+impl Hotkey {
+    /// Grab the key combination on the root window, so that pressing it anywhere sends a
+    /// `KEY_PRESS` event to us.
+    pub fn grab(&self, conn: &Connection, root: xcb::Window) -> Result<(), Error> {
+        let setup = conn.get_setup();
+        let (min, max) = (setup.min_keycode(), setup.max_keycode());
+        let mapping = xcb::get_keyboard_mapping(conn, min, max - min + 1).get_reply()?;
+        let per_keycode = mapping.keysyms_per_keycode() as usize;
+
+        // Also grab with CapsLock and NumLock, which should not affect the combination
+        let locks = [0, xcb::MOD_MASK_LOCK, xcb::MOD_MASK_2,
+            xcb::MOD_MASK_LOCK | xcb::MOD_MASK_2];
+
+        let mut grabbed = false;
+        for (index, keysyms) in mapping.keysyms().chunks(per_keycode).enumerate() {
+            if !keysyms.contains(&self.keysym) {
+                continue;
+            }
+
+            // Fails when another client already grabbed the combination
+            let keycode = min + index as u8;
+            for lock in locks.iter() {
+                xcb::grab_key_checked(conn, false, root, self.modifiers | *lock as u16, keycode,
+                    xcb::GRAB_MODE_ASYNC as u8, xcb::GRAB_MODE_ASYNC as u8).request_check()?;
+            }
+            grabbed = true;
+        }
+
+        if !grabbed {
+            return Err(Error::UnmappedHotkey(self.name.clone()));
+        }
+        Ok(())
+    }
+}
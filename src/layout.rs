@@ -0,0 +1,180 @@
+use crate::Error;
+use crate::leechbar::util::Geometry;
+
+/// Sizes and positions of the parts of the notification window.
+///
+/// This is shared by the X window and by offscreen rendering, so that both look the same.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout {
+    pub border_size: u16,
+    pub border_pad: u16,
+    pub text_width: u16,
+    pub text_height: u16,
+    pub viewport_height: u16,
+    pub countdown_height: u16,
+    pub scrollbar_width: u16,
+    pub corner_radius: u16,
+    pub boxed: bool,
+}
+
+/// Parse a --max-height, which has to leave room for some of the text.
+pub fn parse_max_height(s: &str) -> Result<u16, Error> {
+    s.parse::<u16>().ok()
+        .filter(|height| *height > 0)
+        .ok_or_else(|| Error::InvalidMaxHeight(s.to_owned()))
+}
+
+// How far row `y` of a rounded rectangle of `height` is inset on both sides by its corners
+fn corner_inset(radius: u16, height: u16, y: u16) -> u16 {
+    let row = if y < radius {
+        y
+    } else if y >= height.saturating_sub(radius) {
+        height - 1 - y
+    } else {
+        return 0;
+    };
+
+    // Measure at the middle of the row
+    let (radius, dy) = (radius as f64, radius as f64 - row as f64 - 0.5);
+    (radius - (radius * radius - dy * dy).max(0.0).sqrt()).round() as u16
+}
+
+impl Layout {
+    /// Lay out text of `text_size` at `scale`. The visible part of the text is limited to
+    /// `max_viewport` pixels, and the whole window to `max_height` pixels. The frame's corners
+    /// are rounded with `corner_radius` pixels at scale 1. Unless `boxed`, there is no frame at
+    /// all, only the text.
+    pub fn new(text_size: (u16, u16), scale: f64, max_viewport: Option<u16>,
+        max_height: Option<u16>, corner_radius: u16, boxed: bool) -> Self
+    {
+        let (border_size, border_pad) = if boxed {
+            (std::cmp::max(1, scale.round() as u16), (10.0 * scale).round() as u16)
+        } else {
+            (0, 0)
+        };
+        let decoration = (border_pad + border_size) * 2;
+        let viewport_height = std::cmp::min(text_size.1, std::cmp::min(
+            max_viewport.unwrap_or(u16::MAX),
+            max_height.unwrap_or(u16::MAX).saturating_sub(decoration)));
+
+        let mut layout = Self {
+            border_size,
+            border_pad,
+            text_width: text_size.0,
+            text_height: text_size.1,
+            viewport_height,
+            countdown_height: std::cmp::max(2, (3.0 * scale).round() as u16),
+            scrollbar_width: std::cmp::max(2, (3.0 * scale).round() as u16),
+            corner_radius: 0,
+            boxed,
+        };
+
+        // The corners can't be rounder than the window, or thinner than the border
+        if corner_radius > 0 && boxed {
+            let max = std::cmp::min(layout.width(), layout.height()) / 2;
+            let radius = (corner_radius as f64 * scale).round() as u16;
+            layout.corner_radius = std::cmp::min(max, std::cmp::max(border_size, radius));
+        }
+
+        layout
+    }
+
+    pub fn width(&self) -> u16 {
+        self.text_width + (self.border_pad + self.border_size) * 2
+    }
+
+    pub fn height(&self) -> u16 {
+        self.viewport_height + (self.border_pad + self.border_size) * 2
+    }
+
+    /// Where the top left corner of the text goes.
+    pub fn text_offset(&self) -> i16 {
+        (self.border_pad + self.border_size) as i16
+    }
+
+    /// How far the text can be scrolled until its bottom is visible.
+    pub fn max_scroll(&self) -> u16 {
+        self.text_height - self.viewport_height
+    }
+
+    /// Width in pixels of the countdown bar for the remaining part of the timeout.
+    pub fn countdown_width(&self, remaining: f64) -> u16 {
+        ((self.width() - self.border_size * 2) as f64 * remaining).round() as u16
+    }
+
+    pub fn border(&self) -> Vec<Geometry> {
+        let (width, height, size) = (self.width(), self.height(), self.border_size);
+        let radius = self.corner_radius;
+
+        if !self.boxed {
+            return vec![];
+        }
+
+        if radius == 0 {
+            return vec![
+                Geometry::new(0, 0, width, size),
+                Geometry::new(0, (height - size) as i16, width, size),
+                Geometry::new(0, 0, size, height),
+                Geometry::new((width - size) as i16, 0, size, height),
+            ];
+        }
+
+        // Straight sides between the corners
+        let mut border = vec![
+            Geometry::new(0, radius as i16, size, height - radius * 2),
+            Geometry::new((width - size) as i16, radius as i16, size, height - radius * 2),
+        ];
+
+        // The rows of the corners, between the outer and the inner rounded rectangle
+        for y in (0..radius).chain(height - radius..height) {
+            let outer = corner_inset(radius, height, y);
+            if y < size || y >= height - size {
+                border.push(Geometry::new(outer as i16, y as i16, width - outer * 2, 1));
+                continue;
+            }
+
+            let inner = size + corner_inset(radius - size, height - size * 2, y - size);
+            let span = inner.saturating_sub(outer);
+            border.push(Geometry::new(outer as i16, y as i16, span, 1));
+            border.push(Geometry::new((width - outer - span) as i16, y as i16, span, 1));
+        }
+
+        border
+    }
+
+    /// The outline of the window, with the corners cut away when they are rounded.
+    pub fn shape(&self) -> Vec<Geometry> {
+        let (width, height, radius) = (self.width(), self.height(), self.corner_radius);
+
+        let mut shape = vec![Geometry::new(0, radius as i16, width, height - radius * 2)];
+        for y in (0..radius).chain(height - radius..height) {
+            let inset = corner_inset(radius, height, y);
+            shape.push(Geometry::new(inset as i16, y as i16, width - inset * 2, 1));
+        }
+
+        shape
+    }
+
+    /// The countdown bar along the bottom edge, `width` pixels wide.
+    pub fn countdown(&self, width: u16) -> Geometry {
+        let y = self.height() - self.border_size - self.countdown_height;
+        Geometry::new(self.border_size as i16, y as i16, width, self.countdown_height)
+    }
+
+    /// The scrollbar thumb centered in the right padding, if the text is scrollable at all and
+    /// there is a frame to hold it.
+    pub fn scrollbar(&self, scroll: u16) -> Option<Geometry> {
+        if self.max_scroll() == 0 || !self.boxed {
+            return None;
+        }
+
+        let viewport = self.viewport_height as u32;
+        let thumb_height = std::cmp::min(viewport, std::cmp::max(self.scrollbar_width as u32,
+            viewport * viewport / self.text_height as u32));
+        let thumb_y = (viewport - thumb_height) * scroll as u32 / self.max_scroll() as u32;
+        let x = self.width() - self.border_size - (self.border_pad + self.scrollbar_width) / 2;
+
+        Some(Geometry::new(x as i16, self.text_offset() + thumb_y as i16, self.scrollbar_width,
+            thumb_height as u16))
+    }
+}
@@ -7,6 +7,31 @@ use super::super::util;
 use std::sync::Arc;
 use crate::leechbar::error::ErrorKind;
 
+/// Effects drawn along with the glyphs of a text.
+#[derive(Clone, Copy, Default)]
+pub struct Effects {
+    /// Width in pixels and color of an outline stroked around the glyphs
+    pub outline: Option<(f64, Color)>,
+    /// Offset in pixels and color of a drop shadow
+    pub shadow: Option<((f64, f64), Color)>,
+}
+
+impl Effects {
+    /// How far the effects reach beyond the text, on the left, top, right and bottom.
+    pub fn margins(&self) -> (u16, u16, u16, u16) {
+        let outline = self.outline.map(|(width, _)| width.ceil() as u16).unwrap_or(0);
+        let (dx, dy) = self.shadow.map(|(offset, _)| offset).unwrap_or((0., 0.));
+        let (dx, dy) = (dx.round() as i32, dy.round() as i32);
+
+        (
+            outline + (-dx).max(0) as u16,
+            outline + (-dy).max(0) as u16,
+            outline + dx.max(0) as u16,
+            outline + dy.max(0) as u16,
+        )
+    }
+}
+
 /// A cached text.
 ///
 /// This creates a text that is cached on the X server. Keeping this around instead of moving it
@@ -21,7 +46,8 @@ impl Text {
     /// Create a new cached text.
     ///
     /// This takes an optional font and color, if these are not set it will use the default font
-    /// and color of the bar.
+    /// and color of the bar. Font sizes in points are rendered at the resolution of `dpi`, and
+    /// the `effects` are drawn within the margins they need.
     ///
     /// # Errors
     ///
@@ -45,6 +71,8 @@ impl Text {
         content: &str,
         font: &FontDescription,
         color: Color,
+        dpi: f64,
+        effects: &Effects,
     ) -> Result<Self, ErrorKind> {
         // It's not possible to create an empty text
         // This returns an error if it is attempted
@@ -56,7 +84,7 @@ impl Text {
         let conn = Arc::clone(&conn);
 
         // Get width and height for text
-        let (w, h) = (text_width(content, font)?, geometry.height);
+        let (w, h) = (text_width(content, font, dpi, effects)?, geometry.height);
         // Create a new pixmap with empty background
         let pix = conn.generate_id();
         xtry!(create_pixmap_checked, &conn, 32, pix, window, w, h);
@@ -80,19 +108,15 @@ impl Text {
 
         // Create context and layout for drawing text
         let context = Context::new(&surface);
-        let layout = layout(&context, content, font);
+        let layout = layout(&context, content, font, dpi);
 
-        // Set font color
-        let color = color.as_fractions();
-        context.set_source_rgba(color.0, color.1, color.2, color.3);
-
-        // Center text horizontally and vertically
+        // Center text vertically, keeping the effects' margins
+        let (left, top, _, bottom) = effects.margins();
         let (_, text_height) = layout.get_pixel_size();
-        let text_y = (f64::from(h) - f64::from(text_height)) / 2.;
-        context.move_to(0., text_y);
+        let text_y = (f64::from(h) - f64::from(text_height) - f64::from(top + bottom)) / 2.;
 
         // Display text
-        pangocairo::functions::show_layout(&context, &layout);
+        draw(&context, &layout, f64::from(left), text_y + f64::from(top), color, effects);
 
         // Create picture from pixmap
         let picture = conn.generate_id();
@@ -111,40 +135,149 @@ impl Text {
     }
 }
 
-// Get the width text will have with the specified font
-fn text_width(text: &str, font: &FontDescription) -> Result<u16, ErrorKind> {
+// Draw the shadow, the outline and then the glyphs of a layout at `(x, y)`
+fn draw(context: &Context, layout: &Layout, x: f64, y: f64, color: Color, effects: &Effects) {
+    let set_color = |color: Color| {
+        let color = color.as_fractions();
+        context.set_source_rgba(color.0, color.1, color.2, color.3);
+    };
+    let outline = effects.outline.map(|(width, _)| width).unwrap_or(0.);
+
+    // The outline is stroked centered on the glyphs' path, so it takes twice the width
+    context.set_line_width(outline * 2.);
+    context.set_line_join(cairo::LineJoin::Round);
+
+    if let Some(((dx, dy), shadow)) = effects.shadow {
+        set_color(shadow);
+        context.move_to(x + dx, y + dy);
+        pangocairo::functions::layout_path(context, layout);
+        if outline > 0. {
+            context.stroke_preserve();
+        }
+        context.fill();
+    }
+
+    if let Some((width, outline)) = effects.outline {
+        if width > 0. {
+            set_color(outline);
+            context.move_to(x, y);
+            pangocairo::functions::layout_path(context, layout);
+            context.stroke();
+        }
+    }
+
+    set_color(color);
+    context.move_to(x, y);
+    pangocairo::functions::show_layout(context, layout);
+}
+
+/// Draw text onto a cairo context with the top left corner of its margins at `pos`.
+///
+/// This renders exactly like a cached `Text`, for drawing without an X.Org connection.
+pub fn show_text(
+    context: &Context,
+    pos: (f64, f64),
+    content: &str,
+    font: &FontDescription,
+    color: Color,
+    dpi: f64,
+    effects: &Effects,
+) {
+    let layout = layout(context, content, font, dpi);
+    let (left, top, _, _) = effects.margins();
+    draw(context, &layout, pos.0 + f64::from(left), pos.1 + f64::from(top), color, effects);
+}
+
+/// The parts of rendered text, including its effects, that are mostly opaque.
+///
+/// The text is laid out like `show_text` at `(0, 0)`. Each returned rectangle is a run of pixels
+/// within a single row.
+pub fn mask(
+    content: &str,
+    font: &FontDescription,
+    dpi: f64,
+    effects: &Effects,
+) -> Result<Vec<Geometry>, ErrorKind> {
+    let (w, h) = text_size(content, font, dpi, effects)?;
+    let mut surface = ImageSurface::create(Format::A8, i32::from(w), i32::from(h))
+        .map_err(|e| format!("Unable to create text mask: {:?}", e))?;
+
+    {
+        let context = Context::new(&surface);
+        let white = Color::new(255, 255, 255, 255);
+        show_text(&context, (0., 0.), content, font, white, dpi, effects);
+    }
+    surface.flush();
+
+    let stride = surface.get_stride() as usize;
+    let data = surface.get_data().map_err(|e| format!("Unable to read text mask: {:?}", e))?;
+
+    let mut rects = Vec::new();
+    for y in 0..h as usize {
+        let row = &data[y * stride..y * stride + w as usize];
+        let mut x = 0;
+        while x < row.len() {
+            // Antialiased edges that are mostly transparent are left out
+            if row[x] < 0x40 {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < row.len() && row[x] >= 0x40 {
+                x += 1;
+            }
+            rects.push(Geometry::new(start as i16, y as i16, (x - start) as u16, 1));
+        }
+    }
+
+    Ok(rects)
+}
+
+// Get the width text will have with the specified font and effects
+fn text_width(text: &str, font: &FontDescription, dpi: f64, effects: &Effects) -> Result<u16, ErrorKind> {
     // Create a dummy surface and context
     let surface = ImageSurface::create(Format::ARgb32, 0, 0)
         .map_err(|e| format!("Unable to create dummy layout for font size: {:?}", e))?;
     let context = Context::new(&surface);
 
     // Create the layout
-    let layout = layout(&context, text, font);
+    let layout = layout(&context, text, font, dpi);
 
     // Get the width of the text
     let width = layout.get_pixel_size().0;
+    let (left, _, right, _) = effects.margins();
 
-    Ok(width as u16)
+    Ok(width as u16 + left + right)
 }
 
-pub fn text_size(text: &str, font: &FontDescription) -> Result<(u16, u16), ErrorKind> {
+pub fn text_size(
+    text: &str,
+    font: &FontDescription,
+    dpi: f64,
+    effects: &Effects,
+) -> Result<(u16, u16), ErrorKind> {
     // Create a dummy surface and context
     let surface = ImageSurface::create(Format::ARgb32, 0, 0)
         .map_err(|e| format!("Unable to create dummy layout for font size: {:?}", e))?;
     let context = Context::new(&surface);
 
     // Create the layout
-    let layout = layout(&context, text, font);
+    let layout = layout(&context, text, font, dpi);
 
-    // Get the width of the text
+    // Get the size of the text, with room for the effects
     let size = layout.get_pixel_size();
+    let (left, top, right, bottom) = effects.margins();
 
-    Ok((size.0 as u16, size.1 as u16))
+    Ok((size.0 as u16 + left + right, size.1 as u16 + top + bottom))
 }
 
-// Create a layout with the font and text
-fn layout(context: &Context, text: &str, font: &FontDescription) -> Layout {
+// Create a layout with the font and text, at the given font resolution
+fn layout(context: &Context, text: &str, font: &FontDescription, dpi: f64) -> Layout {
     let layout = pangocairo::functions::create_layout(context).expect("Unable to create layout.");
+    if let Some(pango_context) = layout.get_context() {
+        pangocairo::functions::context_set_resolution(&pango_context, dpi);
+        layout.context_changed();
+    }
     layout.set_text(text);
     layout.set_font_description(Some(font));
     layout
@@ -17,6 +17,12 @@ impl Geometry {
             height,
         }
     }
+
+    // Whether a point lies within the geometry
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x as i32 && x < self.x as i32 + self.width as i32
+            && y >= self.y as i32 && y < self.y as i32 + self.height as i32
+    }
 }
 
 impl Default for Geometry {
@@ -5,16 +5,40 @@ use structopt::StructOpt;
 use thiserror::Error;
 use std::path::PathBuf;
 use xcb::{Connection, xproto::Screen, randr};
-use std::io::Read;
+use std::io::{Read, Write};
 use std::sync::Arc;
 use std::time::Duration;
 
+mod bell;
+mod bindings;
+mod blink;
+mod color;
+mod dedupe;
+mod dnd;
+mod ewmh;
+mod fonts;
+mod history;
+mod keys;
+mod layout;
 mod leechbar;
+mod png;
+mod scale;
+mod selection;
+mod snap;
+mod stacking;
+mod state;
+mod timeout;
+mod workarea;
 
 #[derive(StructOpt, Debug)]
+#[structopt(after_help = "SUBCOMMANDS:\n    \
+    xnotify history    List the notifications shown recently\n    \
+    xnotify dnd        Turn do not disturb on or off for the display\n\n\
+    To show a message that starts with the word history or dnd, put -- before it.",
+    group = structopt::clap::ArgGroup::with_name("dismissal").multiple(true))]
 struct Opt {
     /// Time to wait until message automatically gets off the screen
-    #[structopt(long = "timeout", short="t")]
+    #[structopt(long = "timeout", short="t", group = "dismissal")]
     timeout: Option<f32>,
 
     /// Take text from file instead of standard input. If file is '-', takes from standard input.
@@ -22,31 +46,259 @@ struct Opt {
     from_file: Option<PathBuf>,
 
     /// Font to use (Pango font string, for example "normal 100" for big text)
-    #[structopt(long = "font", short="n", default_value="9x15bold")]
+    #[structopt(long = "font", short="n", default_value="Monospace Bold 11")]
     font: String,
 
+    /// Fail instead of warning when the family of --font is not installed
+    #[structopt(long = "strict-font")]
+    strict_font: bool,
+
+    /// List the font families that can be used with --font, and exit
+    #[structopt(long = "list-fonts")]
+    list_fonts: bool,
+
     /// Make the window flash its colors
     #[structopt(long = "blink", short="l")]
     blink: bool,
 
     /// Duration of the blink.
-    #[structopt(long = "blink-duration", short="d", default_value="0.25")]
-    blink_duration: f32,
+    #[structopt(long = "blink-duration", short="d", default_value="0.25", parse(try_from_str = blink::parse_secs))]
+    blink_duration: Duration,
 
     /// Rate of the blink (time between each color flip)
-    #[structopt(long = "blink-rate", short="e", default_value="0.05")]
-    blink_rate: f32,
+    #[structopt(long = "blink-rate", short="e", default_value="0.05", parse(try_from_str = blink::parse_secs))]
+    blink_rate: Duration,
+
+    /// How the colors change while blinking: square, pulse, border or heartbeat
+    #[structopt(long = "blink-pattern", default_value="square")]
+    blink_pattern: blink::Pattern,
+
+    /// Blink again every <secs> seconds, until the pointer enters or clicks the window. It has to
+    /// be longer than --blink-duration
+    #[structopt(long = "reblink-every", requires = "blink", parse(try_from_str = blink::parse_secs))]
+    reblink_every: Option<Duration>,
+
+    /// Ring the keyboard bell when the notification appears, optionally as --bell=<percent> of
+    /// the base volume, from -100 to 100
+    #[structopt(long = "bell", require_equals = true)]
+    bell: Option<Option<i8>>,
+
+    /// Name of the bell, for sound themes that play their own sounds for bells
+    #[structopt(long = "bell-name", requires = "bell")]
+    bell_name: Option<String>,
+
+    /// Ring the bell again whenever --reblink-every blinks
+    #[structopt(long = "bell-on-reblink", requires_all = &["bell", "reblink-every"])]
+    bell_on_reblink: bool,
+
+    /// Instead of showing another window, count a repeat on a window with the same tag, or the
+    /// same text if untagged, that was shown within <secs> seconds. It shows "(×N)" and its
+    /// timeout starts over
+    #[structopt(long = "dedupe")]
+    dedupe: Option<u64>,
+
+    /// Background color while blinking
+    #[structopt(long = "blink-background", default_value="white", parse(try_from_str = color::parse))]
+    blink_background: leechbar::util::Color,
+
+    /// Text color while blinking
+    #[structopt(long = "blink-text", default_value="white", parse(try_from_str = color::parse))]
+    blink_text: leechbar::util::Color,
+
+    /// Border color while blinking. The border pattern flashes the inverse of the normal border
+    /// color instead when they're the same
+    #[structopt(long = "blink-border", default_value="white", parse(try_from_str = color::parse))]
+    blink_border: leechbar::util::Color,
 
     /// Initial screen position
     #[structopt(long = "position", short="p", default_value="%50,%50")]
     position: String,
+
+    /// Place relative to the whole monitor, ignoring the work area and panel struts
+    #[structopt(long = "ignore-struts")]
+    ignore_struts: bool,
+
+    /// Scale factor for padding, border and font resolution. Detected from Xft.dpi or the
+    /// monitor's physical size if not given
+    #[structopt(long = "scale", parse(try_from_str = scale::parse))]
+    scale: Option<f64>,
+
+    /// Draw a bar along the bottom edge that shrinks until the timeout expires
+    #[structopt(long = "show-countdown")]
+    show_countdown: bool,
+
+    /// Minimum time left of the timeout after the pointer leaves the window. The timeout is
+    /// paused while the pointer is inside
+    #[structopt(long = "hover-grace", default_value="0")]
+    hover_grace: f32,
+
+    /// Distance in pixels at which a dragged window snaps to monitor and work area edges. Zero
+    /// disables snapping
+    #[structopt(long = "snap-distance", default_value="16")]
+    snap_distance: u16,
+
+    /// Tag identifying the kind of notification
+    #[structopt(long = "tag", default_value="")]
+    tag: String,
+
+    /// Show at the position where the last notification with the same tag was dragged to, and
+    /// remember where this one gets dragged to
+    #[structopt(long = "remember-position")]
+    remember_position: bool,
+
+    /// How important the notification is: low, normal or critical
+    #[structopt(long = "urgency", short="u", default_value="normal")]
+    urgency: history::Urgency,
+
+    /// Don't log the notification to the history in $XDG_STATE_HOME/xnotify/history.jsonl
+    #[structopt(long = "no-history")]
+    no_history: bool,
+
+    /// Maximum height in pixels of the visible text. Taller text can be scrolled with the mouse
+    /// wheel. The height is always limited to the monitor's work area
+    #[structopt(long = "max-height", parse(try_from_str = layout::parse_max_height))]
+    max_height: Option<u16>,
+
+    /// Radius in pixels of the frame's rounded corners. The window is shaped to match, so that
+    /// no compositor is needed
+    #[structopt(long = "corner-radius", default_value="0")]
+    corner_radius: u16,
+
+    /// Show only the text, without background or frame, like osd_cat. The window is shaped to
+    /// the text, so this is best combined with --outline or --shadow
+    #[structopt(long = "no-box")]
+    no_box: bool,
+
+    /// Stroke an outline of <px> pixels around the text, in a color such as "#000000" or black
+    #[structopt(long = "outline", number_of_values = 2, value_names = &["px", "color"])]
+    outline: Vec<String>,
+
+    /// Draw a drop shadow of the text, offset by <dx,dy> pixels, such as "2,2"
+    #[structopt(long = "shadow", number_of_values = 2, value_names = &["dx,dy", "color"])]
+    shadow: Vec<String>,
+
+    /// Time to keep serving copied text after the window closes, unless another client takes
+    /// over the clipboard sooner
+    #[structopt(long = "copy-linger", default_value="30")]
+    copy_linger: f32,
+
+    /// Mouse binding, such as "ctrl+button1=copy". Actions are dismiss, move, pin, copy,
+    /// scroll-up, scroll-down, prev-page, next-page, exec:<command> and none. Defaults are
+    /// button1=move, button2=copy, button3=dismiss, button4=scroll-up and button5=scroll-down
+    #[structopt(long = "bind")]
+    bind: Vec<bindings::Binding>,
+
+    /// Render the notification into a PNG file instead of showing it. This does not need an X
+    /// server, and the scale defaults to 1
+    #[structopt(long = "render-png")]
+    render_png: Option<PathBuf>,
+
+    /// Let the window manager manage the window, as a notification that stays on top, instead of
+    /// bypassing it
+    #[structopt(long = "managed")]
+    managed: bool,
+
+    /// Raise the window again when other windows get raised over it
+    #[structopt(long = "keep-on-top")]
+    keep_on_top: bool,
+
+    /// Maximum number of times per minute that --keep-on-top raises the window
+    #[structopt(long = "raise-limit", default_value="10")]
+    raise_limit: usize,
+
+    /// Let all pointer events pass through the window to the windows below. It can then only be
+    /// dismissed by the timeout or --dismiss-key, so one of them is required
+    #[structopt(long = "click-through", requires = "dismissal")]
+    click_through: bool,
+
+    /// Key combination that dismisses the notification from anywhere, such as ctrl+alt+Escape
+    #[structopt(long = "dismiss-key", group = "dismissal")]
+    dismiss_key: Option<keys::Hotkey>,
+
+    /// Message to show, joined by spaces. If not given, it is taken from standard input or from
+    /// the file given to --from-file. It can't be given while standard input is a pipe or a file
+    #[structopt(name = "MESSAGE", conflicts_with = "from-file")]
+    message: Vec<String>,
+
+    // Replaying a queued notification, whose text comes from standard input instead of MESSAGE
+    // or --from-file. The original arguments follow
+    #[structopt(long = "queued", hidden = true)]
+    queued: bool,
+
+    // Where to show the window instead of --position, for reshowing it from the history
+    #[structopt(skip)]
+    origin: Option<(i16, i16)>,
+
+    // The command line arguments, as logged to the history and the queue
+    #[structopt(skip)]
+    args: Vec<String>,
+}
+
+/// Turn do not disturb on or off for the display, or show whether it is on
+#[derive(StructOpt, Debug)]
+#[structopt(name = "xnotify", bin_name = "xnotify dnd")]
+enum DndCommand {
+    /// Hold back notifications, depending on their urgency
+    On(DndOn),
+    /// Show notifications again, starting with the queued ones
+    Off,
+    /// Show whether do not disturb is on, and how many notifications are queued
+    Status,
+}
+
+#[derive(StructOpt, Debug)]
+struct DndOn {
+    /// What happens to low urgency notifications: show, queue or suppress
+    #[structopt(long = "low", default_value="suppress")]
+    low: dnd::Action,
+
+    /// What happens to normal urgency notifications: show, queue or suppress
+    #[structopt(long = "normal", default_value="queue")]
+    normal: dnd::Action,
+
+    /// What happens to critical notifications: show, queue or suppress
+    #[structopt(long = "critical", default_value="show")]
+    critical: dnd::Action,
+}
+
+/// List the notifications shown recently, oldest first
+#[derive(StructOpt, Debug)]
+#[structopt(name = "xnotify", bin_name = "xnotify history")]
+struct HistoryOpt {
+    /// Only list notifications shown within this age, such as 90s, 30m, 2h or 7d
+    #[structopt(long = "since")]
+    since: Option<String>,
+
+    /// Only list notifications with this tag
+    #[structopt(long = "tag")]
+    tag: Option<String>,
+
+    /// Only list notifications of this urgency
+    #[structopt(long = "urgency", short="u")]
+    urgency: Option<history::Urgency>,
+
+    /// Only list notifications whose text matches this regular expression
+    #[structopt(long = "text")]
+    text: Option<String>,
+
+    /// Maximum number of notifications to list
+    #[structopt(long = "count", short="c", default_value="20")]
+    count: usize,
+
+    /// Show the N-th listed notification again, counted from the most recent one, with its
+    /// original options and position
+    #[structopt(long = "reshow", name = "N")]
+    reshow: Option<usize>,
 }
 
 #[derive(Error, Debug)]
-enum Error {
+pub enum Error {
     #[error("Io error; {0}")]
     IoError(#[from] std::io::Error),
 
+    #[error("Json error; {0}")]
+    JsonError(#[from] serde_json::Error),
+
     #[error("ParseInt error: {0}")]
     ParseIntError(#[from] std::num::ParseIntError),
 
@@ -61,6 +313,66 @@ enum Error {
 
     #[error("No X connection")]
     NoConnection,
+
+    #[error("Message is empty")]
+    EmptyMessage,
+
+    #[error("Message given both as arguments and on standard input")]
+    ConflictingMessage,
+
+    #[error("Invalid binding '{0}'")]
+    InvalidBinding(String),
+
+    #[error("Invalid hotkey '{0}'")]
+    InvalidHotkey(String),
+
+    #[error("No key on the keyboard for hotkey '{0}'")]
+    UnmappedHotkey(String),
+
+    #[error("Font family '{0}' not found")]
+    UnknownFont(String),
+
+    #[error("Invalid color '{0}'")]
+    InvalidColor(String),
+
+    #[error("Invalid do not disturb action '{0}'")]
+    InvalidDndAction(String),
+
+    #[error("Invalid options in the history; {0}")]
+    InvalidHistory(String),
+
+    #[error("Invalid regular expression; {0}")]
+    InvalidRegex(#[from] regex::Error),
+
+    #[error("Invalid age '{0}'")]
+    InvalidAge(String),
+
+    #[error("No notification {0} in the history")]
+    NoSuchNotification(usize),
+
+    #[error("Invalid urgency '{0}'")]
+    InvalidUrgency(String),
+
+    #[error("Invalid maximum height '{0}'")]
+    InvalidMaxHeight(String),
+
+    #[error("Invalid scale '{0}'")]
+    InvalidScale(String),
+
+    #[error("Invalid duration '{0}'")]
+    InvalidDuration(String),
+
+    #[error("Invalid reblink interval '{0}', which has to be longer than the blink duration")]
+    InvalidReblink(String),
+
+    #[error("Invalid blink pattern '{0}'")]
+    InvalidBlinkPattern(String),
+
+    #[error("Invalid text effect '{0}'")]
+    InvalidEffect(String),
+
+    #[error("Render error; {0}")]
+    RenderError(String),
 }
 
 fn main() {
@@ -73,7 +385,7 @@ fn main() {
     }
 }
 
-fn get_largest_window(conn: &Connection, screen: &Screen) -> Result<((i16, i16), (u16, u16)), Error> {
+fn get_monitors(conn: &Connection, screen: &Screen) -> Result<Vec<leechbar::util::Geometry>, Error> {
     let window_dummy = conn.generate_id();
 
     xcb::create_window(&conn, 0, window_dummy, screen.root(), 0, 0, 1, 1, 0, 0, 0, &[]);
@@ -88,21 +400,34 @@ fn get_largest_window(conn: &Connection, screen: &Screen) -> Result<((i16, i16),
         crtc_cookies.push(randr::get_crtc_info(&conn, *crtc, 0));
     }
 
-    let mut res = Err(Error::NoScreenFound);
-    let mut size = 0 as u64;
+    let mut res = vec![];
 
     for crtc_cookie in crtc_cookies.into_iter() {
         if let Ok(reply) = crtc_cookie.get_reply() {
-            let pixels = reply.width() as u64 * reply.height()as u64 ;
-            if pixels > size {
-                size = pixels;
-                res = Ok(((reply.x(), reply.y()), (reply.width(), reply.height())));
+            if reply.width() > 0 && reply.height() > 0 {
+                res.push(leechbar::util::Geometry::new(
+                    reply.x(), reply.y(), reply.width(), reply.height()));
             }
         }
     }
 
     xcb::destroy_window(&conn, window_dummy);
 
+    Ok(res)
+}
+
+fn get_largest_window(monitors: &[leechbar::util::Geometry]) -> Result<leechbar::util::Geometry, Error> {
+    let mut res = Err(Error::NoScreenFound);
+    let mut size = 0 as u64;
+
+    for monitor in monitors {
+        let pixels = monitor.width as u64 * monitor.height as u64;
+        if pixels > size {
+            size = pixels;
+            res = Ok(*monitor);
+        }
+    }
+
     res
 }
 
@@ -124,38 +449,141 @@ fn create_gc_32(conn: &Connection, window: u32) -> Result<u32, Error> {
     Ok(gc)
 }
 
-fn draw(blink_state: bool, conn: &Connection, win: u32,
-    frame: u32, black: u32, window_pict: u32,
-    border_size: u16, border_pad: u16, total_width: u16, total_height: u16,
-    text: &leechbar::component::text::Text) -> Result<(), Error>
-{
-    xcb::poly_fill_rectangle(&conn, win, if blink_state { frame } else { black },
-        &[xcb::Rectangle::new(
-            0, 0,
-            total_width + (border_pad * 2 + border_size),
-            total_height + (border_pad * 2 + border_size))
-        ]);
-
-    xcb::poly_rectangle(&conn, win, frame,
-        &[xcb::Rectangle::new(
-            0, 0,
-            total_width + (border_pad * 2 + border_size),
-            total_height + (border_pad * 2 + border_size))
-        ]);
-
-    let op = xcb::render::PICT_OP_OVER as u8;
-    let pw = text.arc.geometry.width;
-    let ph = text.arc.geometry.height;
-
-    xcb::render::composite_checked(
-        &conn, op, text.arc.xid, 0, window_pict,
-        0, 0, 0, 0,
-        (border_pad + border_size) as i16, (border_pad + border_size) as i16, pw, ph
-    ).request_check()?;
+// Convert a geometry to an X rectangle
+fn rect(geometry: leechbar::util::Geometry) -> xcb::Rectangle {
+    xcb::Rectangle::new(geometry.x, geometry.y, geometry.width, geometry.height)
+}
 
-    conn.flush();
+// Everything needed to paint the notification window
+struct Painter<'a> {
+    conn: &'a Connection,
+    win: u32,
+    window_pict: u32,
+    layout: layout::Layout,
+    text: leechbar::component::text::Text,
+    // The text in the blink color, if that differs from the normal one
+    blink_text: Option<leechbar::component::text::Text>,
+    text_shape: Vec<xcb::Rectangle>,
+}
 
-    Ok(())
+// Convert a color to one of the RENDER extension, with 16 bits per channel
+fn render_color(color: leechbar::util::Color) -> xcb::render::Color {
+    let channel = |c: u8| c as u16 * 257;
+    xcb::render::Color::new(channel(color.red), channel(color.green), channel(color.blue),
+        channel(color.alpha))
+}
+
+impl<'a> Painter<'a> {
+    // Shape the window to its rounded frame, or to the visible part of the text if it has no box
+    fn reshape(&self, scroll: u16) -> Result<(), Error> {
+        let (rects, offset) = if !self.layout.boxed {
+            let offset = self.layout.text_offset();
+            (self.text_shape.clone(), (offset, offset - scroll as i16))
+        } else if self.layout.corner_radius > 0 {
+            (self.layout.shape().iter().map(|g| rect(*g)).collect(), (0, 0))
+        } else {
+            return Ok(());
+        };
+
+        xcb::shape::rectangles_checked(self.conn, xcb::shape::SO_SET as u8,
+            xcb::shape::SK_BOUNDING as u8, xcb::CLIP_ORDERING_UNSORTED as u8, self.win, offset.0,
+            offset.1, &rects).request_check()?;
+
+        Ok(())
+    }
+
+    fn fill(&self, color: leechbar::util::Color, rects: &[xcb::Rectangle]) {
+        xcb::render::fill_rectangles(self.conn, xcb::render::PICT_OP_SRC as u8, self.window_pict,
+            render_color(color), rects);
+    }
+
+    // Composite a text onto the window, faded to `opacity` unless that is 1
+    fn composite(&self, text: &leechbar::component::text::Text, opacity: f64, scroll: u16)
+        -> Result<(), Error>
+    {
+        let conn = self.conn;
+        let layout = &self.layout;
+        let pw = text.arc.geometry.width;
+        let ph = std::cmp::min(text.arc.geometry.height, layout.viewport_height);
+        let offset = layout.text_offset();
+
+        let mask = if opacity < 1. {
+            let mask = conn.generate_id();
+            let alpha = (opacity * 255.).round() as u8;
+            xcb::render::create_solid_fill(conn, mask,
+                render_color(leechbar::util::Color::new(0, 0, 0, alpha)));
+            mask
+        } else {
+            0
+        };
+
+        xcb::render::composite_checked(
+            conn, xcb::render::PICT_OP_OVER as u8, text.arc.xid, mask, self.window_pict,
+            0, scroll as i16, 0, 0,
+            offset, offset, pw, ph
+        ).request_check()?;
+
+        if mask != 0 {
+            xcb::render::free_picture(conn, mask);
+        }
+
+        Ok(())
+    }
+
+    fn draw(&self, paint: &blink::Paint, countdown_width: Option<u16>, scroll: u16)
+        -> Result<(), Error>
+    {
+        let layout = &self.layout;
+
+        self.fill(paint.background, &[xcb::Rectangle::new(0, 0, layout.width(), layout.height())]);
+
+        let border: Vec<_> = layout.border().iter().map(|g| rect(*g)).collect();
+        self.fill(paint.border, &border);
+
+        if let Some(countdown_width) = countdown_width {
+            self.fill(paint.bars, &[rect(layout.countdown(countdown_width))]);
+        }
+
+        if let Some(scrollbar) = layout.scrollbar(scroll) {
+            self.fill(paint.bars, &[rect(scrollbar)]);
+        }
+
+        // Fade from the normal to the blink text
+        match &self.blink_text {
+            Some(blink_text) if paint.text >= 1. => self.composite(blink_text, 1., scroll)?,
+            Some(blink_text) if paint.text > 0. => {
+                self.composite(&self.text, 1. - paint.text, scroll)?;
+                self.composite(blink_text, paint.text, scroll)?;
+            },
+            _ => self.composite(&self.text, 1., scroll)?,
+        }
+
+        self.conn.flush();
+
+        Ok(())
+    }
+}
+
+// The outline and shadow of the text, in pixels at `scale`. The last one counts if given more
+// than once
+fn parse_effects(opt: &Opt, scale: f64) -> Result<leechbar::component::text::Effects, Error> {
+    let parse = |v: &str| v.trim().parse::<f64>().map_err(|_| Error::InvalidEffect(v.to_owned()));
+
+    let outline = match opt.outline.chunks(2).last() {
+        Some([width, color]) => Some((parse(width)? * scale, color::parse(color)?)),
+        _ => None,
+    };
+
+    let shadow = match opt.shadow.chunks(2).last() {
+        Some([offset, color]) => {
+            let (dx, dy) = offset.split_once(',')
+                .ok_or_else(|| Error::InvalidEffect(offset.to_owned()))?;
+            Some(((parse(dx)? * scale, parse(dy)? * scale), color::parse(color)?))
+        },
+        _ => None,
+    };
+
+    Ok(leechbar::component::text::Effects { outline, shadow })
 }
 
 fn parse_position(v: &str, measure: u16, screen_measure: u16) -> Result<i16, Error>
@@ -171,8 +599,181 @@ fn parse_position(v: &str, measure: u16, screen_measure: u16) -> Result<i16, Err
     Ok(std::cmp::max(0, std::cmp::min(min, pos)))
 }
 
+// The options of an invocation logged to the history or the queue, showing `text`
+fn stored_opt(args: &[String], text: &str) -> Result<Opt, Error> {
+    let all_args = std::iter::once("xnotify".to_owned()).chain(args.iter().cloned());
+    let mut opt = Opt::from_iter_safe(all_args).map_err(|e| Error::InvalidHistory(e.message))?;
+    opt.message = vec![text.to_owned()];
+    opt.from_file = None;
+    opt.args = args.to_vec();
+    Ok(opt)
+}
+
+fn dnd_command(command: &DndCommand) -> Result<(), Error> {
+    let (conn, screen_num) = xcb::Connection::connect(None).map_err(|_| Error::NoConnection)?;
+    let root = conn.get_setup().roots().nth(screen_num as usize)
+        .ok_or(Error::NoScreenFound)?.root();
+
+    match command {
+        DndCommand::On(actions) => {
+            dnd::set(&conn, root, Some(&dnd::Settings {
+                low: actions.low,
+                normal: actions.normal,
+                critical: actions.critical,
+            }))?;
+        },
+        DndCommand::Off => {
+            dnd::set(&conn, root, None)?;
+
+            // Show all queued notifications at once, each in a process of its own that outlives
+            // this one
+            let exe = std::env::current_exe()?;
+            dnd::replay_queue(|queued| {
+                let mut child = std::process::Command::new(&exe)
+                    .arg("--queued")
+                    .args(&queued.args)
+                    .stdin(std::process::Stdio::piped())
+                    .stdout(std::process::Stdio::null())
+                    .spawn()?;
+                child.stdin.take().unwrap().write_all(queued.text.as_bytes())?;
+                Ok(())
+            })?;
+        },
+        DndCommand::Status => {
+            match dnd::get(&conn, root)? {
+                Some(settings) => println!("on ({})", settings.describe()),
+                None => println!("off"),
+            }
+
+            let queue = dnd::queue();
+            if let Some(oldest) = queue.first() {
+                println!("{} queued, the oldest since {} UTC", queue.len(),
+                    history::format_time(oldest.queued));
+            }
+        },
+    }
+
+    Ok(())
+}
+
+// List the history, or reshow one of its notifications
+fn history_command(query: &HistoryOpt) -> Result<(), Error> {
+    let since = match &query.since {
+        Some(age) => history::now().saturating_sub(history::parse_age(age)?),
+        None => 0,
+    };
+
+    let text = match &query.text {
+        Some(text) => Some(regex::Regex::new(text)?),
+        None => None,
+    };
+
+    let records: Vec<history::Record> = history::load().into_iter()
+        .filter(|record| record.shown >= since)
+        .filter(|record| query.tag.iter().all(|tag| record.tag == *tag))
+        .filter(|record| query.urgency.iter().all(|urgency| record.urgency == *urgency))
+        .filter(|record| text.iter().all(|text| text.is_match(&record.text)))
+        .collect();
+
+    if let Some(n) = query.reshow {
+        let record = n.checked_sub(1).and_then(|i| records.iter().rev().nth(i))
+            .ok_or(Error::NoSuchNotification(n))?;
+
+        let mut opt = stored_opt(&record.args, &record.text)?;
+        opt.origin = Some((record.x, record.y));
+        opt.no_history = true;
+        return show(opt);
+    }
+
+    let start = records.len().saturating_sub(query.count);
+    for (i, record) in records.iter().enumerate().skip(start) {
+        let tag = if record.tag.is_empty() { String::new() } else { format!(" [{}]", record.tag) };
+        println!("{:>3}  {}  {:<8} {:<7}{}  {}", records.len() - i,
+            history::format_time(record.shown), record.urgency.as_str(), record.reason.as_str(),
+            tag, record.text.lines().next().unwrap_or(""));
+    }
+
+    Ok(())
+}
+
+// Whether standard input is a pipe or a file, rather than a terminal or /dev/null
+fn stdin_has_input() -> bool {
+    use std::os::unix::{fs::FileTypeExt, io::FromRawFd};
+
+    // Borrow the descriptor, without closing it when done
+    let stdin = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(0) });
+    match stdin.metadata() {
+        Ok(metadata) => metadata.file_type().is_fifo() || metadata.file_type().is_file(),
+        Err(_) => false,
+    }
+}
+
 fn main_wrap() -> Result<(), Error> {
-    let opt = Opt::from_args();
+    let args: Vec<String> = std::env::args().collect();
+
+    // Only the exact words are subcommands, so that any other message can be given as arguments
+    match args.get(1).map(String::as_str) {
+        Some("history") => history_command(&HistoryOpt::from_iter(&args[1..])),
+        Some("dnd") => dnd_command(&DndCommand::from_iter(&args[1..])),
+        _ => {
+            let mut opt = Opt::from_iter(&args);
+            if !opt.message.is_empty() && !opt.queued && stdin_has_input() {
+                return Err(Error::ConflictingMessage);
+            }
+            let original = if opt.queued { 2 } else { 1 };
+            opt.args = args[original..].to_vec();
+            show(opt)
+        },
+    }
+}
+
+// Show the notification
+fn show(opt: Opt) -> Result<(), Error> {
+    // Otherwise every blink would start while the previous one still runs
+    if let Some(every) = opt.reblink_every.filter(|every| *every <= opt.blink_duration) {
+        return Err(Error::InvalidReblink(every.as_secs_f32().to_string()));
+    }
+
+    if opt.list_fonts {
+        for family in fonts::families() {
+            println!("{}", family);
+        }
+        return Ok(());
+    }
+
+    let pango_font = pango::FontDescription::from_string(&opt.font);
+    fonts::check(&pango_font, opt.strict_font)?;
+
+    // A queued notification that is replayed always takes its text from standard input
+    let from_file = opt.from_file.as_ref().filter(|f| f.as_os_str() != "-" && !opt.queued);
+    let text = if !opt.message.is_empty() && !opt.queued {
+        opt.message.join(" ")
+    } else if let Some(from_file) = from_file {
+        std::fs::read_to_string(from_file)?
+    } else {
+        let mut buffer = String::new();
+        let stdin = std::io::stdin();
+        let mut handle = stdin.lock();
+        handle.read_to_string(&mut buffer)?;
+        buffer
+    };
+
+    if text.trim().is_empty() {
+        return Err(Error::EmptyMessage);
+    }
+    let text_lines = text.lines().count();
+
+    if let Some(path) = &opt.render_png {
+        let scale = opt.scale.unwrap_or(1.0);
+        let dpi = scale * scale::BASE_DPI;
+        let effects = parse_effects(&opt, scale)?;
+        let text_size = leechbar::component::text::text_size(&text, &pango_font, dpi, &effects)
+            .unwrap();
+        let layout = layout::Layout::new(text_size, scale, opt.max_height, None,
+            opt.corner_radius, !opt.no_box);
+        let countdown = opt.show_countdown && opt.timeout.is_some();
+        return png::render(path, &text, &pango_font, &layout, dpi, &effects, countdown);
+    }
 
     let (conn, screen_num) = if let Ok(x) = xcb::Connection::connect(None) {
         x
@@ -183,56 +784,102 @@ fn main_wrap() -> Result<(), Error> {
     let conn = Arc::new(conn);
     let setup = conn.get_setup();
     let screen = setup.roots().nth(screen_num as usize).unwrap();
+
+    if let Some(dnd) = dnd::get(&conn, screen.root())? {
+        match dnd.action(opt.urgency) {
+            dnd::Action::Show => {},
+            dnd::Action::Queue => return dnd::enqueue(&text, &opt.args),
+            dnd::Action::Suppress => return Ok(()),
+        }
+    }
+
+    let dedupe_key = dedupe::key(&opt.tag, &text);
+    let dedupe = match opt.dedupe {
+        Some(max_age) => {
+            let dedupe = dedupe::Dedupe::new(&conn)?;
+            if let Some(existing) = dedupe.find(&conn, screen.root(), &dedupe_key, max_age)? {
+                dedupe.send_repeat(&conn, existing);
+                return Ok(());
+            }
+            Some(dedupe)
+        },
+        None => None,
+    };
+
     let foreground = conn.generate_id();
-    let frame = conn.generate_id();
-    let black = conn.generate_id();
-    let pango_font = pango::FontDescription::from_string(&opt.font);
 
-    let text = if let Some(from_file) = opt.from_file {
-        std::fs::read_to_string(from_file)?
+    let monitors = get_monitors(&conn, &screen)?;
+    let largest_window = get_largest_window(&monitors)?;
+    let usable_area = |monitor| if opt.ignore_struts {
+        Ok(monitor)
     } else {
-        let mut buffer = String::new();
-        let stdin = std::io::stdin();
-        let mut handle = stdin.lock();
-        handle.read_to_string(&mut buffer)?;
-        buffer
+        workarea::usable_area(&conn, &screen, monitor)
     };
+    let area = usable_area(largest_window)?;
+
+    let scale = match opt.scale {
+        Some(scale) => scale,
+        None => scale::detect(&conn, &screen, largest_window)?,
+    };
+    let dpi = scale * scale::BASE_DPI;
+    let effects = parse_effects(&opt, scale)?;
 
     let (format24, format32) = leechbar::util::formats::image_formats(&conn);
-    let (text_width, text_height) =
-        leechbar::component::text::text_size(&text, &pango_font).unwrap();
-    let border_size = 1;
-    let border_pad = 10;
+    let layout_for = |content: &str| {
+        let text_size = leechbar::component::text::text_size(content, &pango_font, dpi, &effects)
+            .unwrap();
+        layout::Layout::new(text_size, scale, opt.max_height, Some(area.height),
+            opt.corner_radius, !opt.no_box)
+    };
+    let layout = layout_for(&text);
 
     xcb::create_gc(&conn, foreground, screen.root(), &[
         (xcb::GC_FOREGROUND, screen.white_pixel()),
         (xcb::GC_GRAPHICS_EXPOSURES, 0),
     ]).request_check()?;
 
-    xcb::create_gc(&conn, frame, screen.root(), &[
-        (xcb::GC_FOREGROUND, screen.white_pixel()),
-        (xcb::GC_GRAPHICS_EXPOSURES, 0),
-    ]).request_check()?;
-
-    xcb::create_gc(&conn, black, screen.root(), &[
-        (xcb::GC_FOREGROUND, screen.black_pixel()),
-        (xcb::GC_GRAPHICS_EXPOSURES, 0),
-    ]).request_check()?;
-
-    let largest_window = get_largest_window(&conn, &screen)?;
-    let total_width = text_width + (border_pad + border_size) * 2;
-    let total_height = text_height + (border_pad + border_size) * 2;
+    let total_width = layout.width();
+    let total_height = layout.height();
 
     // Create the window
     let win = conn.generate_id();
     let (mut pos_x, mut pos_y) = if let Some((pos_x, pos_y)) = opt.position.split_once(",") {
-        let x = parse_position(pos_x, total_width, largest_window.1.0)?;
-        let y = parse_position(pos_y, total_height, largest_window.1.1)?;
-        ((largest_window.0.0 + x) as i16, (largest_window.0.1 + y) as i16)
+        let x = parse_position(pos_x, total_width, area.width)?;
+        let y = parse_position(pos_y, total_height, area.height)?;
+        ((area.x + x) as i16, (area.y + y) as i16)
     } else {
         return Err(Error::InvalidPosition);
     };
 
+    let origin = match opt.origin {
+        Some(origin) => Some(origin),
+        None if opt.remember_position => state::load_position(&opt.tag),
+        None => None,
+    };
+    // Keep a window at `pos` within the usable area of the monitor that its center is on
+    let clamp_to_monitor = |pos: (i16, i16), size: (u16, u16)| {
+        let center = (pos.0 as i32 + size.0 as i32 / 2, pos.1 as i32 + size.1 as i32 / 2);
+        match monitors.iter().find(|m| m.contains(center.0, center.1)) {
+            Some(monitor) => Ok(Some(snap::clamp(pos, size, usable_area(*monitor)?))),
+            None => Ok::<_, Error>(None),
+        }
+    };
+    if let Some(pos) = origin {
+        if let Some((x, y)) = clamp_to_monitor(pos, (total_width, total_height))? {
+            pos_x = x;
+            pos_y = y;
+        }
+    }
+
+    // Edges that a dragged window snaps to
+    let mut snap_areas = vec![];
+    if opt.snap_distance > 0 {
+        for monitor in &monitors {
+            snap_areas.push(*monitor);
+            snap_areas.push(usable_area(*monitor)?);
+        }
+    }
+
     xcb::create_window(&conn,
         xcb::WINDOW_CLASS_COPY_FROM_PARENT as u8,
         win,
@@ -244,29 +891,94 @@ fn main_wrap() -> Result<(), Error> {
         xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
         screen.root_visual(), &[
             (xcb::CW_BACK_PIXEL, screen.black_pixel()),
-            (xcb::CW_OVERRIDE_REDIRECT, 1),
+            (xcb::CW_OVERRIDE_REDIRECT, if opt.managed { 0 } else { 1 }),
             (xcb::CW_EVENT_MASK,
              xcb::EVENT_MASK_EXPOSURE |
              xcb::EVENT_MASK_STRUCTURE_NOTIFY |
+             xcb::EVENT_MASK_VISIBILITY_CHANGE |
              xcb::EVENT_MASK_POINTER_MOTION |
+             xcb::EVENT_MASK_ENTER_WINDOW |
+             xcb::EVENT_MASK_LEAVE_WINDOW |
              xcb::EVENT_MASK_BUTTON_PRESS |
              xcb::EVENT_MASK_BUTTON_RELEASE),
         ]
     ).request_check()?;
 
+    if opt.managed {
+        ewmh::set_notification_hints(&conn, win, (pos_x, pos_y), (total_width, total_height))?;
+    }
+
+    if opt.click_through {
+        // An empty input region, so the pointer never hits the window
+        xcb::shape::rectangles_checked(&conn, xcb::shape::SO_SET as u8,
+            xcb::shape::SK_INPUT as u8, xcb::CLIP_ORDERING_UNSORTED as u8, win, 0, 0, &[])
+            .request_check()?;
+    }
+
+    if let Some(dedupe) = &dedupe {
+        dedupe.mark(&conn, win, &dedupe_key);
+    }
+
+    if let Some(hotkey) = &opt.dismiss_key {
+        hotkey.grab(&conn, screen.root())?;
+    }
+
     conn.flush();
 
     let gcontext = create_gc_32(&conn, win)?;
-    let geometry = leechbar::util::Geometry::new(0, 0, text_width, text_height);
-    let color = leechbar::util::Color::new(255, 255, 255, 255);
-    let text = leechbar::component::text::Text::new(
-        conn.clone(), geometry, gcontext, win, format32, &text, &pango_font, color,
-    ).unwrap();
+    let blink = blink::Blink {
+        pattern: opt.blink_pattern,
+        rate: opt.blink_rate,
+        duration: opt.blink_duration,
+        normal: blink::Colors {
+            background: leechbar::util::Color::new(0, 0, 0, 255),
+            text: leechbar::util::Color::new(255, 255, 255, 255),
+            border: leechbar::util::Color::new(255, 255, 255, 255),
+        },
+        blink: blink::Colors {
+            background: opt.blink_background,
+            text: opt.blink_text,
+            border: opt.blink_border,
+        },
+    };
+    let mut selection = selection::Selection::new(&conn, win, &text)?;
+    let message = text.clone();
+
+    // The cached texts for `content`, and the shape of the text if the window has no box
+    let texts_for = |content: &str, layout: &layout::Layout| {
+        let geometry = leechbar::util::Geometry::new(0, 0, layout.text_width, layout.text_height);
+        let cached_text = |color| leechbar::component::text::Text::new(
+            conn.clone(), geometry, gcontext, win, format32, content, &pango_font, color, dpi,
+            &effects,
+        ).unwrap();
+
+        let text = cached_text(blink.normal.text);
+        let blink_text = if opt.blink && blink.blink.text != blink.normal.text {
+            Some(cached_text(blink.blink.text))
+        } else {
+            None
+        };
+        let text_shape = if layout.boxed {
+            vec![]
+        } else {
+            leechbar::component::text::mask(content, &pango_font, dpi, &effects).unwrap()
+                .into_iter().map(rect).collect()
+        };
+
+        (text, blink_text, text_shape)
+    };
 
     let window_pict = conn.generate_id();
     xcb::render::create_picture_checked(&conn, window_pict, win, format24, &[])
         .request_check()?;
 
+    let bell = match opt.bell {
+        Some(percent) => {
+            Some(bell::Bell::new(&conn, win, percent.unwrap_or(0), opt.bell_name.as_deref())?)
+        },
+        None => None,
+    };
+
     // Map window while preserving focus on the currently focused application.
     let data = xcb::get_input_focus(&conn);
     let r = data.get_reply()?;
@@ -274,40 +986,78 @@ fn main_wrap() -> Result<(), Error> {
     xcb::set_input_focus(&conn, r.revert_to(), r.focus(), xcb::CURRENT_TIME).request_check()?;
     conn.flush();
 
-    // Main loop
-    let start_time = std::time::Instant::now();
-    let mut blink_state = false;
+    let mut keep_on_top = if opt.keep_on_top {
+        Some(stacking::KeepOnTop::new(&conn, screen.root(), win, opt.raise_limit)?)
+    } else {
+        None
+    };
 
-    let mut dur = match opt.timeout {
-        Some(t) => Some(Duration::from_millis((1000.0 * t) as u64)),
-        None => None,
+    let (text, blink_text, text_shape) = texts_for(&message, &layout);
+    let mut painter = Painter {
+        conn: &conn, win, window_pict, layout, text, blink_text, text_shape,
     };
+    painter.reshape(0)?;
+
+    // Scroll by three lines for each turn of the mouse wheel
+    let scroll_step = |layout: &layout::Layout| {
+        (3 * layout.text_height as usize / std::cmp::max(1, text_lines)) as u16
+    };
+
+    // Main loop
+    let mut blink_start = std::time::Instant::now();
+    let mut reblink_every = opt.reblink_every;
+
+    if let Some(bell) = &bell {
+        bell.ring(&conn);
+    }
+    let mut blink_level = 0.;
+    let mut paint = blink.paint(blink_level);
+
+    let mut timeout = opt.timeout.map(timeout::Timeout::from_secs);
+    let mut countdown_width = None;
+    let bindings = bindings::Bindings::new(&opt.bind);
     let mut grab_pointer_coords = None;
+    let mut grab_button = None;
     let mut pending_configure = false;
+    let mut moved = false;
+    let mut scroll = 0;
+    let mut destroyed = false;
+    let mut repeats = 1;
+    let mut reason = history::Reason::Timeout;
+    let shown = history::now();
 
-    while match dur {
-        Some(dur) => start_time.elapsed() < dur,
-        None => true
-    } {
+    while !matches!(&timeout, Some(timeout) if timeout.expired()) {
         std::thread::sleep(Duration::from_millis(1));
 
+        let new_countdown_width = match &timeout {
+            Some(timeout) if opt.show_countdown && painter.layout.boxed =>
+                Some(painter.layout.countdown_width(timeout.remaining_fraction())),
+            _ => None,
+        };
+        let countdown_changed = new_countdown_width != countdown_width;
+        countdown_width = new_countdown_width;
+
         if opt.blink {
-            let elapsed = start_time.elapsed();
-
-            let new_blink_state = if elapsed.as_millis() <= (opt.blink_duration * 1000.0) as u128 {
-                elapsed.div_f32(opt.blink_rate).as_secs() % 2 == 0
-            } else {
-                false
-            };
-
-            if new_blink_state != blink_state {
-                blink_state = new_blink_state;
-                draw(blink_state, &conn, win, frame, black, window_pict, border_size, border_pad,
-                    text_width, text_height, &text)?;
+            if matches!(reblink_every, Some(every) if blink_start.elapsed() >= every) {
+                blink_start = std::time::Instant::now();
+                if let Some(bell) = bell.as_ref().filter(|_| opt.bell_on_reblink) {
+                    bell.ring(&conn);
+                }
+            }
+
+            let new_blink_level = blink.level(blink_start.elapsed());
+
+            if new_blink_level != blink_level || countdown_changed {
+                blink_level = new_blink_level;
+                paint = blink.paint(blink_level);
+                painter.draw(&paint, countdown_width, scroll)?;
             }
         } else {
-            draw(blink_state, &conn, win, frame, black, window_pict, border_size, border_pad,
-                text_width, text_height, &text)?;
+            painter.draw(&paint, countdown_width, scroll)?;
+        }
+
+        if let Some(keep_on_top) = &mut keep_on_top {
+            keep_on_top.poll(&conn)?;
         }
 
         let event = if let Some(event) = conn.poll_for_event() {
@@ -316,12 +1066,59 @@ fn main_wrap() -> Result<(), Error> {
             continue;
         };
 
+        if selection.handle_event(&conn, &event)? {
+            continue;
+        }
+
+        if let Some(dedupe) = dedupe.as_ref().filter(|dedupe| dedupe.is_repeat(&event)) {
+            repeats += 1;
+            let content = format!("{} (×{})", message, repeats);
+            let layout = layout_for(&content);
+            let (text, blink_text, text_shape) = texts_for(&content, &layout);
+            painter = Painter { layout, text, blink_text, text_shape, ..painter };
+
+            // Grow the window from its top left corner, staying on the monitor
+            let size = (layout.width(), layout.height());
+            if let Some((x, y)) = clamp_to_monitor((pos_x, pos_y), size)? {
+                pos_x = x;
+                pos_y = y;
+            }
+            xcb::configure_window(&conn, win, &[
+                (xcb::CONFIG_WINDOW_X as u16, pos_x as u32),
+                (xcb::CONFIG_WINDOW_Y as u16, pos_y as u32),
+                (xcb::CONFIG_WINDOW_WIDTH as u16, size.0 as u32),
+                (xcb::CONFIG_WINDOW_HEIGHT as u16, size.1 as u32),
+            ]);
+            if opt.managed {
+                ewmh::set_notification_hints(&conn, win, (pos_x, pos_y), size)?;
+            }
+            scroll = std::cmp::min(scroll, layout.max_scroll());
+            painter.reshape(scroll)?;
+            dedupe.touch(&conn, win);
+
+            // Start over, unless the notification was pinned, and stay paused while hovered
+            if let Some(timeout) = &mut timeout {
+                timeout.restart();
+            }
+            blink_start = std::time::Instant::now();
+            painter.draw(&paint, countdown_width, scroll)?;
+            continue;
+        }
+
+        if let Some(keep_on_top) = &mut keep_on_top {
+            if keep_on_top.handle_event(&conn, &event)? {
+                continue;
+            }
+        }
+
         let r = event.response_type() & !0x80;
         match r {
             xcb::CONFIGURE_NOTIFY => {
                 pending_configure = false;
-                draw(blink_state, &conn, win, frame, black, window_pict, border_size, border_pad,
-                    text_width, text_height, &text)?;
+                painter.draw(&paint, countdown_width, scroll)?;
+            },
+            xcb::EXPOSE => {
+                painter.draw(&paint, countdown_width, scroll)?;
             },
             xcb::MOTION_NOTIFY => {
                 let event: &xcb::MotionNotifyEvent = unsafe { xcb::cast_event(&event) };
@@ -330,9 +1127,14 @@ fn main_wrap() -> Result<(), Error> {
                     if let Some((px, py)) = grab_pointer_coords {
                         let (x, y) = (event.event_x(), event.event_y());
 
-                        if x != px || y != py {
-                            pos_x = event.root_x() - px;
-                            pos_y = event.root_y() - py;
+                        let (new_x, new_y) = snap::snap(
+                            (event.root_x() - px, event.root_y() - py),
+                            (painter.layout.width(), painter.layout.height()), &snap_areas, opt.snap_distance);
+
+                        if (x != px || y != py) && (new_x != pos_x || new_y != pos_y) {
+                            pos_x = new_x;
+                            pos_y = new_y;
+                            moved = true;
 
                             xcb::configure_window(&conn, win, &[
                                 (xcb::CONFIG_WINDOW_X as u16, pos_x as u32),
@@ -344,35 +1146,132 @@ fn main_wrap() -> Result<(), Error> {
                     }
                 }
             },
+            xcb::ENTER_NOTIFY => {
+                // The user has noticed the notification
+                reblink_every = None;
+                if let Some(timeout) = &mut timeout {
+                    timeout.pause();
+                }
+            },
+            xcb::LEAVE_NOTIFY => {
+                if let Some(timeout) = &mut timeout {
+                    timeout.resume(Duration::from_millis((1000.0 * opt.hover_grace) as u64));
+                }
+            },
+            xcb::KEY_PRESS => {
+                reason = history::Reason::Key;
+                break;
+            },
             xcb::BUTTON_PRESS => {
                 let event: &xcb::ButtonPressEvent = unsafe { xcb::cast_event(&event) };
-                let button = event.detail() as u32;
-                if button == xcb::BUTTON_INDEX_3 {
-                    break;
-                } else if button == xcb::BUTTON_INDEX_1 {
-                    let event_mask = xcb::EVENT_MASK_POINTER_MOTION
-                        | xcb::EVENT_MASK_BUTTON_RELEASE;
-                    grab_pointer_coords = Some((event.event_x(), event.event_y()));
-                    xcb::grab_pointer(&conn, true, screen.root(), event_mask as u16,
-                        xcb::GRAB_MODE_ASYNC as u8, xcb::GRAB_MODE_ASYNC as u8, 0, 0,
-                        xcb::CURRENT_TIME);
-                    dur = None;
+                reblink_every = None;
+                let button = event.detail();
+                let page = painter.layout.viewport_height;
+
+                let scroll_to = |delta: i32| {
+                    let max = painter.layout.max_scroll() as i32;
+                    std::cmp::max(0, std::cmp::min(max, scroll as i32 + delta)) as u16
+                };
+
+                match bindings.action(button, event.state()) {
+                    bindings::Action::Dismiss => {
+                        reason = history::Reason::Click;
+                        break;
+                    },
+                    bindings::Action::Move => {
+                        let event_mask = xcb::EVENT_MASK_POINTER_MOTION
+                            | xcb::EVENT_MASK_BUTTON_RELEASE;
+                        grab_pointer_coords = Some((event.event_x(), event.event_y()));
+                        grab_button = Some(button);
+                        xcb::grab_pointer(&conn, true, screen.root(), event_mask as u16,
+                            xcb::GRAB_MODE_ASYNC as u8, xcb::GRAB_MODE_ASYNC as u8, 0, 0,
+                            xcb::CURRENT_TIME);
+                        timeout = None;
+                    },
+                    bindings::Action::Pin => {
+                        timeout = None;
+                    },
+                    bindings::Action::Copy => {
+                        selection.take(&conn, event.time())?;
+                    },
+                    bindings::Action::Exec(command) => {
+                        std::process::Command::new("sh")
+                            .arg("-c").arg(command)
+                            .env("XNOTIFY_TEXT", &message)
+                            .spawn()?;
+                    },
+                    action @ bindings::Action::ScrollUp |
+                    action @ bindings::Action::ScrollDown |
+                    action @ bindings::Action::PrevPage |
+                    action @ bindings::Action::NextPage => {
+                        if painter.layout.max_scroll() > 0 {
+                            scroll = scroll_to(match action {
+                                bindings::Action::ScrollUp => -(scroll_step(&painter.layout) as i32),
+                                bindings::Action::ScrollDown => scroll_step(&painter.layout) as i32,
+                                bindings::Action::PrevPage => -(page as i32),
+                                _ => page as i32,
+                            });
+                            timeout = None;
+                            painter.reshape(scroll)?;
+                            painter.draw(&paint, countdown_width, scroll)?;
+                        }
+                    },
+                    bindings::Action::Nothing => {},
                 }
             },
             xcb::BUTTON_RELEASE => {
                 let event: &xcb::ButtonReleaseEvent = unsafe { xcb::cast_event(&event) };
-                let button = event.detail() as u32;
-                if button == xcb::BUTTON_INDEX_1 {
+                if grab_button == Some(event.detail()) {
                     xcb::ungrab_pointer(&conn, xcb::CURRENT_TIME);
                     grab_pointer_coords = None;
+                    grab_button = None;
                 }
             },
             xcb::DESTROY_NOTIFY => {
-                break;
+                let event: &xcb::DestroyNotifyEvent = unsafe { xcb::cast_event(&event) };
+                if event.window() == win {
+                    destroyed = true;
+                    reason = history::Reason::Destroy;
+                    break;
+                }
             },
             _ => {}
         }
     }
 
+    if opt.remember_position && moved {
+        state::save_position(&opt.tag, (pos_x, pos_y))?;
+    }
+
+    if !opt.no_history {
+        history::append(&history::Record {
+            text: message.clone(),
+            tag: opt.tag.clone(),
+            urgency: opt.urgency,
+            shown,
+            closed: history::now(),
+            reason,
+            x: pos_x,
+            y: pos_y,
+            args: opt.args.clone(),
+        })?;
+    }
+
+    // Keep serving copied text until another client takes over the selections
+    if selection.is_owned() && !destroyed {
+        xcb::unmap_window(&conn, win);
+        conn.flush();
+
+        let linger_start = std::time::Instant::now();
+        let linger = Duration::from_millis((1000.0 * opt.copy_linger) as u64);
+
+        while selection.is_owned() && linger_start.elapsed() < linger {
+            std::thread::sleep(Duration::from_millis(1));
+            if let Some(event) = conn.poll_for_event() {
+                selection.handle_event(&conn, &event)?;
+            }
+        }
+    }
+
     Ok(())
 }
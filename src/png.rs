@@ -0,0 +1,70 @@
+use crate::Error;
+use crate::layout::Layout;
+use crate::leechbar::component::text;
+use crate::leechbar::util::{Color, Geometry};
+use cairo::{Context, Format, ImageSurface};
+use pango::FontDescription;
+use std::path::Path;
+
+// The colors of the X window, which uses the screen's black and white pixels
+const BLACK: Color = Color { red: 0, green: 0, blue: 0, alpha: 255 };
+const WHITE: Color = Color { red: 255, green: 255, blue: 255, alpha: 255 };
+
+fn fill(context: &Context, color: Color, rects: &[Geometry]) {
+    let color = color.as_fractions();
+    context.set_source_rgba(color.0, color.1, color.2, color.3);
+    for rect in rects {
+        context.rectangle(rect.x as f64, rect.y as f64, rect.width as f64, rect.height as f64);
+    }
+    context.fill();
+}
+
+/// Render the notification as it first appears on the screen into a PNG file, without
+/// connecting to the X server.
+///
+/// Without a box, the background is transparent, and so are the corners that rounding cuts away
+/// from the window.
+pub fn render(path: &Path, content: &str, font: &FontDescription, layout: &Layout, dpi: f64,
+    effects: &text::Effects, countdown: bool) -> Result<(), Error>
+{
+    let rounded = layout.boxed && layout.corner_radius > 0;
+    let format = if layout.boxed && !rounded { Format::Rgb24 } else { Format::ARgb32 };
+    let surface = ImageSurface::create(format, layout.width() as i32, layout.height() as i32)
+        .map_err(|e| Error::RenderError(e.to_string()))?;
+    let context = Context::new(&surface);
+
+    // Like the window's shape, which clips everything drawn into it
+    if rounded {
+        for rect in layout.shape() {
+            context.rectangle(rect.x as f64, rect.y as f64, rect.width as f64, rect.height as f64);
+        }
+        context.clip();
+    }
+
+    if layout.boxed {
+        fill(&context, BLACK, &[Geometry::new(0, 0, layout.width(), layout.height())]);
+    }
+    fill(&context, WHITE, &layout.border());
+    if countdown && layout.boxed {
+        fill(&context, WHITE, &[layout.countdown(layout.countdown_width(1.0))]);
+    }
+    if let Some(scrollbar) = layout.scrollbar(0) {
+        fill(&context, WHITE, &[scrollbar]);
+    }
+
+    // Only the viewport of the text is visible
+    let offset = layout.text_offset() as f64;
+    context.save();
+    context.rectangle(offset, offset, layout.text_width as f64, layout.viewport_height as f64);
+    context.clip();
+    text::show_text(&context, (offset, offset), content, font, WHITE, dpi, effects);
+    context.restore();
+
+    drop(context);
+    surface.flush();
+
+    let mut file = std::fs::File::create(path)?;
+    surface.write_to_png(&mut file).map_err(|e| Error::RenderError(e.to_string()))?;
+
+    Ok(())
+}
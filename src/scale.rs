@@ -0,0 +1,80 @@
+use crate::Error;
+use crate::leechbar::util::Geometry;
+use xcb::{Connection, xproto::Screen, randr};
+
+/// The resolution that unscaled sizes, such as the default padding and border, are designed for.
+pub const BASE_DPI: f64 = 96.0;
+
+/// Parse a scale factor given with --scale, which has to be positive.
+pub fn parse(s: &str) -> Result<f64, Error> {
+    s.parse::<f64>().ok()
+        .filter(|scale| scale.is_finite() && *scale > 0.0)
+        .ok_or_else(|| Error::InvalidScale(s.to_owned()))
+}
+
+// The `Xft.dpi` resource, as loaded by `xrdb` into the root window's `RESOURCE_MANAGER`
+fn xft_dpi(conn: &Connection, screen: &Screen) -> Result<Option<f64>, Error> {
+    let reply = xcb::get_property(conn, false, screen.root(), xcb::ATOM_RESOURCE_MANAGER,
+        xcb::ATOM_STRING, 0, 0x40000).get_reply()?;
+    let resources = String::from_utf8_lossy(reply.value::<u8>());
+
+    for line in resources.lines() {
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim() == "Xft.dpi" {
+                if let Ok(dpi) = value.trim().parse::<f64>() {
+                    if dpi > 0.0 {
+                        return Ok(Some(dpi));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+// Horizontal DPI derived from the physical size of the RandR output that shows `monitor`
+fn output_dpi(conn: &Connection, screen: &Screen, monitor: Geometry) -> Result<Option<f64>, Error> {
+    let resources = randr::get_screen_resources(conn, screen.root()).get_reply()?;
+
+    for crtc in resources.crtcs() {
+        let info = match randr::get_crtc_info(conn, *crtc, 0).get_reply() {
+            Ok(info) => info,
+            Err(_) => continue,
+        };
+
+        if Geometry::new(info.x(), info.y(), info.width(), info.height()) != monitor {
+            continue;
+        }
+
+        for output in info.outputs() {
+            let output = randr::get_output_info(conn, *output, resources.config_timestamp())
+                .get_reply()?;
+            if output.mm_width() > 0 {
+                let inches = output.mm_width() as f64 / 25.4;
+                return Ok(Some(monitor.width as f64 / inches));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Detects the scale factor to apply to sizes and font resolution on `monitor`.
+///
+/// `Xft.dpi` is preferred, as it is what the user configured for all other X applications. The
+/// physical size of the monitor is only a fallback, and it is never used to scale down, since
+/// many monitors report inaccurate dimensions.
+pub fn detect(conn: &Connection, screen: &Screen, monitor: Geometry) -> Result<f64, Error> {
+    if let Some(dpi) = xft_dpi(conn, screen)? {
+        return Ok(dpi / BASE_DPI);
+    }
+
+    if let Some(dpi) = output_dpi(conn, screen, monitor)? {
+        // Round to quarter steps so that a slightly off physical size doesn't yield odd sizes
+        let scale = (dpi / BASE_DPI * 4.0).round() / 4.0;
+        return Ok(scale.max(1.0));
+    }
+
+    Ok(1.0)
+}
@@ -0,0 +1,106 @@
+use crate::Error;
+use crate::ewmh::intern_atom;
+use xcb::Connection;
+
+/// Ownership of the CLIPBOARD and PRIMARY selections, serving the notification text.
+pub struct Selection {
+    window: xcb::Window,
+    text: String,
+    clipboard: xcb::Atom,
+    targets: xcb::Atom,
+    utf8_string: xcb::Atom,
+    text_atom: xcb::Atom,
+    owned: Vec<xcb::Atom>,
+}
+
+impl Selection {
+    pub fn new(conn: &Connection, window: xcb::Window, text: &str) -> Result<Self, Error> {
+        Ok(Self {
+            window,
+            text: text.to_owned(),
+            clipboard: intern_atom(conn, "CLIPBOARD")?,
+            targets: intern_atom(conn, "TARGETS")?,
+            utf8_string: intern_atom(conn, "UTF8_STRING")?,
+            text_atom: intern_atom(conn, "TEXT")?,
+            owned: vec![],
+        })
+    }
+
+    /// Whether another client still has to take over one of the selections.
+    pub fn is_owned(&self) -> bool {
+        !self.owned.is_empty()
+    }
+
+    /// Become the owner of both selections, as of the `time` of the triggering event.
+    pub fn take(&mut self, conn: &Connection, time: xcb::Timestamp) -> Result<(), Error> {
+        self.owned.clear();
+
+        for selection in [self.clipboard, xcb::ATOM_PRIMARY].iter() {
+            xcb::set_selection_owner(conn, self.window, *selection, time);
+            let owner = xcb::get_selection_owner(conn, *selection).get_reply()?.owner();
+            if owner == self.window {
+                self.owned.push(*selection);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Handle selection related events. Returns `false` for events that are not about the
+    /// selection.
+    pub fn handle_event(&mut self, conn: &Connection, event: &xcb::GenericEvent) -> Result<bool, Error> {
+        match event.response_type() & !0x80 {
+            xcb::SELECTION_CLEAR => {
+                let event: &xcb::SelectionClearEvent = unsafe { xcb::cast_event(event) };
+                self.owned.retain(|s| *s != event.selection());
+            },
+            xcb::SELECTION_REQUEST => {
+                let event: &xcb::SelectionRequestEvent = unsafe { xcb::cast_event(event) };
+                self.answer(conn, event)?;
+            },
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    fn answer(&self, conn: &Connection, event: &xcb::SelectionRequestEvent) -> Result<(), Error> {
+        // Obsolete clients may not set the property to store the result in
+        let property = if event.property() == xcb::ATOM_NONE {
+            event.target()
+        } else {
+            event.property()
+        };
+
+        let target = event.target();
+        let property = if !self.owned.contains(&event.selection()) {
+            xcb::ATOM_NONE
+        } else if target == self.targets {
+            let targets = [self.targets, self.utf8_string, self.text_atom, xcb::ATOM_STRING];
+            xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, event.requestor(),
+                property, xcb::ATOM_ATOM, 32, &targets);
+            property
+        } else if target == self.utf8_string || target == self.text_atom {
+            xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, event.requestor(),
+                property, self.utf8_string, 8, self.text.as_bytes());
+            property
+        } else if target == xcb::ATOM_STRING {
+            // STRING is Latin-1, replace everything outside of it
+            let latin1: Vec<u8> = self.text.chars()
+                .map(|c| if (c as u32) < 0x100 { c as u8 } else { b'?' })
+                .collect();
+            xcb::change_property(conn, xcb::PROP_MODE_REPLACE as u8, event.requestor(),
+                property, xcb::ATOM_STRING, 8, &latin1);
+            property
+        } else {
+            xcb::ATOM_NONE
+        };
+
+        let notify = xcb::SelectionNotifyEvent::new(event.time(), event.requestor(),
+            event.selection(), target, property);
+        xcb::send_event(conn, false, event.requestor(), 0, &notify);
+        conn.flush();
+
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::leechbar::util::Geometry;
+
+// Snap a single axis: the window spans `pos..pos + size`, and each edge is `(start, length)`
+fn snap_axis(pos: i16, size: u16, edges: &[(i16, u16)], distance: u16) -> i16 {
+    let mut best: Option<(i32, i16)> = None;
+
+    for (start, length) in edges {
+        let candidates = [*start as i32, *start as i32 + *length as i32 - size as i32];
+
+        for candidate in candidates.iter() {
+            let delta = (candidate - pos as i32).abs();
+            if delta <= distance as i32 && !matches!(best, Some((d, _)) if d <= delta) {
+                best = Some((delta, *candidate as i16));
+            }
+        }
+    }
+
+    best.map(|(_, pos)| pos).unwrap_or(pos)
+}
+
+// Whether the ranges `a..a + a_len` and `b..b + b_len` overlap
+fn overlaps(a: i16, a_len: u16, b: i16, b_len: u16) -> bool {
+    (a as i32) < b as i32 + b_len as i32 && (b as i32) < a as i32 + a_len as i32
+}
+
+/// Move a window at `pos` of `size` so that its edges stick to the edges of `areas` when they
+/// are closer than `distance` pixels. Only areas that the window overlaps on the other axis are
+/// considered.
+pub fn snap(pos: (i16, i16), size: (u16, u16), areas: &[Geometry], distance: u16) -> (i16, i16) {
+    if distance == 0 {
+        return pos;
+    }
+
+    let horizontal: Vec<_> = areas.iter()
+        .filter(|a| overlaps(pos.1, size.1, a.y, a.height))
+        .map(|a| (a.x, a.width))
+        .collect();
+    let vertical: Vec<_> = areas.iter()
+        .filter(|a| overlaps(pos.0, size.0, a.x, a.width))
+        .map(|a| (a.y, a.height))
+        .collect();
+
+    (snap_axis(pos.0, size.0, &horizontal, distance), snap_axis(pos.1, size.1, &vertical, distance))
+}
+
+/// Move a window at `pos` of `size` so that it lies within `area`, as far as it fits.
+pub fn clamp(pos: (i16, i16), size: (u16, u16), area: Geometry) -> (i16, i16) {
+    let clamp_axis = |pos: i16, size: u16, start: i16, length: u16| {
+        let max = start as i32 + length.saturating_sub(size) as i32;
+        std::cmp::max(start as i32, std::cmp::min(max, pos as i32)) as i16
+    };
+
+    (clamp_axis(pos.0, size.0, area.x, area.width), clamp_axis(pos.1, size.1, area.y, area.height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MONITOR: Geometry = Geometry { x: 0, y: 0, width: 1000, height: 800 };
+    const SIZE: (u16, u16) = (100, 50);
+
+    #[test]
+    fn snaps_to_near_edges() {
+        assert_eq!(snap((10, 12), SIZE, &[MONITOR], 16), (0, 0));
+        assert_eq!(snap((890, 740), SIZE, &[MONITOR], 16), (900, 750));
+        assert_eq!(snap((-8, 400), SIZE, &[MONITOR], 16), (0, 400));
+    }
+
+    #[test]
+    fn leaves_far_edges_alone() {
+        assert_eq!(snap((17, 400), SIZE, &[MONITOR], 16), (17, 400));
+        assert_eq!(snap((10, 12), SIZE, &[MONITOR], 0), (10, 12));
+    }
+
+    #[test]
+    fn snaps_to_the_nearest_edge() {
+        let work_area = Geometry::new(0, 30, 1000, 770);
+        assert_eq!(snap((400, 20), SIZE, &[MONITOR, work_area], 30), (400, 30));
+        assert_eq!(snap((400, 10), SIZE, &[MONITOR, work_area], 30), (400, 0));
+    }
+
+    #[test]
+    fn ignores_areas_beside_the_window() {
+        let other = Geometry::new(1000, 800, 1000, 800);
+        assert_eq!(snap((400, 790), SIZE, &[other], 16), (400, 790));
+    }
+
+    #[test]
+    fn clamps_into_the_area() {
+        assert_eq!(clamp((400, 300), SIZE, MONITOR), (400, 300));
+        assert_eq!(clamp((-20, -5), SIZE, MONITOR), (0, 0));
+        assert_eq!(clamp((950, 790), SIZE, MONITOR), (900, 750));
+    }
+
+    #[test]
+    fn clamps_a_window_larger_than_the_area_to_its_start() {
+        let area = Geometry::new(10, 20, 50, 30);
+        assert_eq!(clamp((100, 100), SIZE, area), (10, 20));
+    }
+}
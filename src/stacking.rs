@@ -0,0 +1,145 @@
+use crate::Error;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+use xcb::Connection;
+
+// The period over which raises are counted for the rate limit
+const RATE_PERIOD: Duration = Duration::from_secs(60);
+
+/// Keeps a window, or its window manager frame, above windows that are raised or mapped after it.
+///
+/// Raising is rate limited, so that two clients doing the same don't keep raising over each other
+/// at full speed.
+pub struct KeepOnTop {
+    win: xcb::Window,
+    root: xcb::Window,
+    limit: usize,
+    raises: VecDeque<Instant>,
+    obscured: bool,
+}
+
+impl KeepOnTop {
+    /// Start watching the stacking of the root's children, allowing `limit` raises per minute.
+    pub fn new(conn: &Connection, root: xcb::Window, win: xcb::Window, limit: usize) -> Result<Self, Error> {
+        xcb::change_window_attributes_checked(conn, root, &[
+            (xcb::CW_EVENT_MASK, xcb::EVENT_MASK_SUBSTRUCTURE_NOTIFY),
+        ]).request_check()?;
+
+        Ok(Self {
+            win,
+            root,
+            limit,
+            raises: VecDeque::new(),
+            obscured: false,
+        })
+    }
+
+    // The window itself, or the frame that a window manager reparented it into, whichever is the
+    // root's child
+    fn frame(&self, conn: &Connection) -> Result<xcb::Window, Error> {
+        let mut window = self.win;
+        loop {
+            let parent = xcb::query_tree(conn, window).get_reply()?.parent();
+            if parent == self.root || parent == xcb::NONE {
+                return Ok(window);
+            }
+            window = parent;
+        }
+    }
+
+    // Whether a mapped window stacked above ours overlaps it
+    fn is_obscured(&self, conn: &Connection, frame: xcb::Window) -> Result<bool, Error> {
+        let ours = xcb::get_geometry(conn, frame).get_reply()?;
+        let tree = xcb::query_tree(conn, self.root).get_reply()?;
+        let above = tree.children().iter().skip_while(|w| **w != frame).skip(1);
+
+        for window in above {
+            let attrs = match xcb::get_window_attributes(conn, *window).get_reply() {
+                Ok(attrs) => attrs,
+                Err(_) => continue,
+            };
+            if attrs.map_state() != xcb::MAP_STATE_VIEWABLE as u8 {
+                continue;
+            }
+
+            let theirs = match xcb::get_geometry(conn, *window).get_reply() {
+                Ok(geometry) => geometry,
+                Err(_) => continue,
+            };
+            let overlaps = |a: i16, a_len: u16, b: i16, b_len: u16| {
+                (a as i32) < b as i32 + b_len as i32 && (b as i32) < a as i32 + a_len as i32
+            };
+            if overlaps(ours.x(), ours.width(), theirs.x(), theirs.width())
+                && overlaps(ours.y(), ours.height(), theirs.y(), theirs.height())
+            {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    // Another top-level window was restacked, moved or mapped
+    fn check_sibling(&mut self, conn: &Connection, window: xcb::Window) -> Result<(), Error> {
+        let frame = self.frame(conn)?;
+        if window != frame && self.is_obscured(conn, frame)? {
+            self.obscured = true;
+        }
+        Ok(())
+    }
+
+    /// Handle stacking related events. Returns `false` for events that are not about the
+    /// stacking, which includes the window's own configure events.
+    pub fn handle_event(&mut self, conn: &Connection, event: &xcb::GenericEvent) -> Result<bool, Error> {
+        match event.response_type() & !0x80 {
+            xcb::VISIBILITY_NOTIFY => {
+                let event: &xcb::VisibilityNotifyEvent = unsafe { xcb::cast_event(event) };
+                if event.state() != xcb::VISIBILITY_UNOBSCURED as u8 {
+                    self.obscured = true;
+                }
+            },
+            xcb::CONFIGURE_NOTIFY => {
+                let event: &xcb::ConfigureNotifyEvent = unsafe { xcb::cast_event(event) };
+                if event.event() != self.root {
+                    return Ok(false);
+                }
+                self.check_sibling(conn, event.window())?;
+            },
+            xcb::MAP_NOTIFY => {
+                let event: &xcb::MapNotifyEvent = unsafe { xcb::cast_event(event) };
+                if event.event() != self.root {
+                    return Ok(false);
+                }
+                self.check_sibling(conn, event.window())?;
+            },
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Raise the window if it got obscured, unless the rate limit was reached. In that case it is
+    /// raised by a later call.
+    pub fn poll(&mut self, conn: &Connection) -> Result<(), Error> {
+        if !self.obscured {
+            return Ok(());
+        }
+
+        while matches!(self.raises.front(), Some(raised) if raised.elapsed() > RATE_PERIOD) {
+            self.raises.pop_front();
+        }
+        if self.raises.len() >= self.limit {
+            return Ok(());
+        }
+
+        xcb::configure_window(conn, self.win, &[
+            (xcb::CONFIG_WINDOW_STACK_MODE as u16, xcb::STACK_MODE_ABOVE),
+        ]);
+        conn.flush();
+
+        self.raises.push_back(Instant::now());
+        self.obscured = false;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,71 @@
+use crate::Error;
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
+/// Directory of the files that xnotify keeps between invocations, `$XDG_STATE_HOME/xnotify`.
+pub fn dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("state"),
+    };
+
+    Some(base.join("xnotify"))
+}
+
+/// Parse the lines of a JSON-lines file at `path`, skipping damaged lines with a warning.
+pub fn parse_json_lines<T: DeserializeOwned>(path: &Path, content: &str) -> Vec<T> {
+    content.lines().enumerate()
+        .filter_map(|(i, line)| match serde_json::from_str(line) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                eprintln!("Skipping damaged line {} of {}; {}", i + 1, path.display(), e);
+                None
+            },
+        })
+        .collect()
+}
+
+fn positions_path() -> Option<PathBuf> {
+    Some(dir()?.join("positions"))
+}
+
+// Each line of the positions file is "<x> <y> <tag>"
+fn parse_position_line(line: &str) -> Option<(i16, i16, &str)> {
+    let mut parts = line.splitn(3, ' ');
+    let x = parts.next()?.parse().ok()?;
+    let y = parts.next()?.parse().ok()?;
+    Some((x, y, parts.next().unwrap_or("")))
+}
+
+/// The position a notification with `tag` was last dragged to, if any.
+pub fn load_position(tag: &str) -> Option<(i16, i16)> {
+    let content = std::fs::read_to_string(positions_path()?).ok()?;
+
+    content.lines()
+        .filter_map(parse_position_line)
+        .find(|(_, _, t)| *t == tag)
+        .map(|(x, y, _)| (x, y))
+}
+
+/// Remember `pos` as the position for the next notification with `tag`.
+pub fn save_position(tag: &str, pos: (i16, i16)) -> Result<(), Error> {
+    let path = match positions_path() {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    let content = std::fs::read_to_string(&path).unwrap_or_default();
+    let mut lines: Vec<String> = content.lines()
+        .filter(|line| !matches!(parse_position_line(line), Some((_, _, t)) if t == tag))
+        .map(String::from)
+        .collect();
+    lines.push(format!("{} {} {}", pos.0, pos.1, tag));
+
+    // Replace the file atomically, as other notifications may be reading it
+    std::fs::create_dir_all(path.parent().unwrap())?;
+    let tmp = path.with_extension(format!("tmp.{}", std::process::id()));
+    std::fs::write(&tmp, lines.join("\n") + "\n")?;
+    std::fs::rename(&tmp, &path)?;
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::time::{Duration, Instant};
+
+/// Time left until the notification automatically gets off the screen.
+pub struct Timeout {
+    // As given, before any grace extended it
+    full: Duration,
+    duration: Duration,
+    started: Instant,
+    paused: Option<Instant>,
+}
+
+impl Timeout {
+    pub fn new(duration: Duration) -> Self {
+        Self {
+            full: duration,
+            duration,
+            started: Instant::now(),
+            paused: None,
+        }
+    }
+
+    pub fn from_secs(secs: f32) -> Self {
+        Self::new(Duration::from_millis((1000.0 * secs) as u64))
+    }
+
+    // Running time, not counting the current pause
+    fn elapsed(&self) -> Duration {
+        match self.paused {
+            Some(paused) => paused.saturating_duration_since(self.started),
+            None => self.started.elapsed(),
+        }
+    }
+
+    pub fn expired(&self) -> bool {
+        self.paused.is_none() && self.elapsed() >= self.duration
+    }
+
+    /// The part of the timeout that is still left, from 1.0 when started down to 0.0.
+    pub fn remaining_fraction(&self) -> f64 {
+        if self.duration.as_nanos() == 0 {
+            return 0.0;
+        }
+
+        let remaining = self.duration.saturating_sub(self.elapsed());
+        remaining.as_secs_f64() / self.duration.as_secs_f64()
+    }
+
+    /// Stop counting down until `resume` is called.
+    pub fn pause(&mut self) {
+        if self.paused.is_none() {
+            self.paused = Some(Instant::now());
+        }
+    }
+
+    /// Count down the full duration again from now, staying paused if it is.
+    pub fn restart(&mut self) {
+        let now = Instant::now();
+        self.duration = self.full;
+        self.started = now;
+        if self.paused.is_some() {
+            self.paused = Some(now);
+        }
+    }
+
+    /// Continue counting down, leaving at least `grace` of the timeout.
+    pub fn resume(&mut self, grace: Duration) {
+        if self.paused.is_none() {
+            return;
+        }
+
+        let mut elapsed = self.elapsed();
+        self.paused = None;
+
+        if self.duration.saturating_sub(elapsed) < grace {
+            self.duration = std::cmp::max(self.duration, grace);
+            elapsed = self.duration - grace;
+        }
+
+        let now = Instant::now();
+        self.started = now.checked_sub(elapsed).unwrap_or(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A timeout of `duration` seconds that started `ago` seconds ago
+    fn started_ago(duration: u64, ago: u64) -> Timeout {
+        let mut timeout = Timeout::new(Duration::from_secs(duration));
+        timeout.started = Instant::now() - Duration::from_secs(ago);
+        timeout
+    }
+
+    fn remaining(timeout: &Timeout) -> Duration {
+        timeout.duration.saturating_sub(timeout.elapsed())
+    }
+
+    fn assert_about(actual: Duration, expected: Duration) {
+        let difference = std::cmp::max(actual, expected) - std::cmp::min(actual, expected);
+        assert!(difference < Duration::from_millis(100), "{:?} is not about {:?}", actual,
+            expected);
+    }
+
+    #[test]
+    fn expires_after_duration() {
+        assert!(!started_ago(10, 4).expired());
+        assert!(started_ago(10, 11).expired());
+        assert_about(remaining(&started_ago(10, 4)), Duration::from_secs(6));
+    }
+
+    #[test]
+    fn pause_stops_the_countdown() {
+        let mut timeout = started_ago(10, 11);
+        timeout.pause();
+        assert!(!timeout.expired());
+
+        // Paused 3 seconds ago, after running for 1
+        let mut timeout = started_ago(10, 4);
+        timeout.paused = Some(Instant::now() - Duration::from_secs(3));
+        timeout.pause();
+        assert_about(remaining(&timeout), Duration::from_secs(9));
+    }
+
+    #[test]
+    fn resume_without_grace_keeps_the_time_left() {
+        let mut timeout = started_ago(10, 4);
+        timeout.pause();
+        timeout.resume(Duration::from_secs(0));
+
+        assert!(timeout.paused.is_none());
+        assert_about(remaining(&timeout), Duration::from_secs(6));
+    }
+
+    #[test]
+    fn resume_leaves_at_least_the_grace() {
+        let mut timeout = started_ago(10, 9);
+        timeout.pause();
+        timeout.resume(Duration::from_secs(3));
+        assert_about(remaining(&timeout), Duration::from_secs(3));
+
+        // More time than the grace is left alone
+        let mut timeout = started_ago(10, 2);
+        timeout.pause();
+        timeout.resume(Duration::from_secs(3));
+        assert_about(remaining(&timeout), Duration::from_secs(8));
+    }
+
+    #[test]
+    fn resume_extends_a_timeout_shorter_than_the_grace() {
+        let mut timeout = started_ago(1, 2);
+        timeout.pause();
+        timeout.resume(Duration::from_secs(5));
+
+        assert!(!timeout.expired());
+        assert_about(remaining(&timeout), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn restart_keeps_the_pause() {
+        let mut timeout = started_ago(10, 9);
+        timeout.pause();
+        timeout.restart();
+        assert!(timeout.paused.is_some());
+        assert_about(remaining(&timeout), Duration::from_secs(10));
+
+        // The grace that extended it is dropped
+        let mut timeout = started_ago(1, 2);
+        timeout.pause();
+        timeout.resume(Duration::from_secs(5));
+        timeout.restart();
+        assert!(timeout.paused.is_none());
+        assert_about(remaining(&timeout), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn resume_without_pause_does_nothing() {
+        let mut timeout = started_ago(10, 9);
+        timeout.resume(Duration::from_secs(5));
+        assert_about(remaining(&timeout), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,126 @@
+use crate::Error;
+use crate::leechbar::util::Geometry;
+use xcb::{Connection, xproto::Screen};
+
+// Intern an atom, returning `None` if no client has created it yet
+fn existing_atom(conn: &Connection, name: &str) -> Result<Option<xcb::Atom>, Error> {
+    let atom = xcb::intern_atom(conn, true, name).get_reply()?.atom();
+    Ok(if atom == xcb::ATOM_NONE { None } else { Some(atom) })
+}
+
+// Read a CARDINAL[] property, an empty vector if it is not set
+fn cardinals(conn: &Connection, window: xcb::Window, property: xcb::Atom) -> Result<Vec<u32>, Error> {
+    let reply = xcb::get_property(conn, false, window, property, xcb::ATOM_CARDINAL, 0, 1024)
+        .get_reply()?;
+    if reply.format() != 32 {
+        return Ok(vec![]);
+    }
+    Ok(reply.value::<u32>().to_vec())
+}
+
+// Intersection of two rectangles, `None` if they do not overlap
+fn intersect(a: Geometry, b: Geometry) -> Option<Geometry> {
+    let x1 = std::cmp::max(a.x as i32, b.x as i32);
+    let y1 = std::cmp::max(a.y as i32, b.y as i32);
+    let x2 = std::cmp::min(a.x as i32 + a.width as i32, b.x as i32 + b.width as i32);
+    let y2 = std::cmp::min(a.y as i32 + a.height as i32, b.y as i32 + b.height as i32);
+
+    if x2 <= x1 || y2 <= y1 {
+        return None;
+    }
+
+    Some(Geometry::new(x1 as i16, y1 as i16, (x2 - x1) as u16, (y2 - y1) as u16))
+}
+
+// The `_NET_WORKAREA` of the current desktop, if the window manager publishes one
+fn net_workarea(conn: &Connection, root: xcb::Window) -> Result<Option<Geometry>, Error> {
+    let workarea = match existing_atom(conn, "_NET_WORKAREA")? {
+        Some(atom) => cardinals(conn, root, atom)?,
+        None => return Ok(None),
+    };
+
+    let desktop = match existing_atom(conn, "_NET_CURRENT_DESKTOP")? {
+        Some(atom) => cardinals(conn, root, atom)?.first().cloned().unwrap_or(0),
+        None => 0,
+    } as usize;
+
+    let area = match workarea.chunks_exact(4).nth(desktop) {
+        Some(area) => area,
+        None => match workarea.chunks_exact(4).next() {
+            Some(area) => area,
+            None => return Ok(None),
+        }
+    };
+
+    Ok(Some(Geometry::new(area[0] as i16, area[1] as i16, area[2] as u16, area[3] as u16)))
+}
+
+// Shrink `area` by the `_NET_WM_STRUT_PARTIAL` reservations of all mapped top-level windows
+fn apply_struts(conn: &Connection, screen: &Screen, mut area: Geometry) -> Result<Geometry, Error> {
+    let strut_partial = match existing_atom(conn, "_NET_WM_STRUT_PARTIAL")? {
+        Some(atom) => atom,
+        None => return Ok(area),
+    };
+
+    let root_w = screen.width_in_pixels() as i32;
+    let root_h = screen.height_in_pixels() as i32;
+    let tree = xcb::query_tree(conn, screen.root()).get_reply()?;
+
+    for child in tree.children() {
+        let attrs = match xcb::get_window_attributes(conn, *child).get_reply() {
+            Ok(attrs) => attrs,
+            Err(_) => continue,
+        };
+        if attrs.map_state() != xcb::MAP_STATE_VIEWABLE as u8 {
+            continue;
+        }
+
+        let s = cardinals(conn, *child, strut_partial)?;
+        if s.len() < 12 {
+            continue;
+        }
+
+        let (left, right, top, bottom) = (s[0] as i32, s[1] as i32, s[2] as i32, s[3] as i32);
+        let reserved = [
+            (left, Geometry::new(0, s[4] as i16, left as u16, (s[5].saturating_sub(s[4]) + 1) as u16)),
+            (right, Geometry::new((root_w - right) as i16, s[6] as i16, right as u16, (s[7].saturating_sub(s[6]) + 1) as u16)),
+            (top, Geometry::new(s[8] as i16, 0, (s[9].saturating_sub(s[8]) + 1) as u16, top as u16)),
+            (bottom, Geometry::new(s[10] as i16, (root_h - bottom) as i16, (s[11].saturating_sub(s[10]) + 1) as u16, bottom as u16)),
+        ];
+
+        for (edge, (size, rect)) in reserved.iter().enumerate() {
+            if *size <= 0 || intersect(area, *rect).is_none() {
+                continue;
+            }
+
+            let (x1, y1) = (area.x as i32, area.y as i32);
+            let (x2, y2) = (x1 + area.width as i32, y1 + area.height as i32);
+            let (x1, y1, x2, y2) = match edge {
+                0 => (std::cmp::max(x1, *size), y1, x2, y2),
+                1 => (x1, y1, std::cmp::min(x2, root_w - *size), y2),
+                2 => (x1, std::cmp::max(y1, *size), x2, y2),
+                _ => (x1, y1, x2, std::cmp::min(y2, root_h - *size)),
+            };
+
+            if x2 > x1 && y2 > y1 {
+                area = Geometry::new(x1 as i16, y1 as i16, (x2 - x1) as u16, (y2 - y1) as u16);
+            }
+        }
+    }
+
+    Ok(area)
+}
+
+/// Returns the part of `monitor` that is not covered by panels and docks, as announced by the
+/// window manager through `_NET_WORKAREA` and by docks through `_NET_WM_STRUT_PARTIAL`.
+pub fn usable_area(conn: &Connection, screen: &Screen, monitor: Geometry) -> Result<Geometry, Error> {
+    let mut area = monitor;
+
+    if let Some(workarea) = net_workarea(conn, screen.root())? {
+        if let Some(inside) = intersect(area, workarea) {
+            area = inside;
+        }
+    }
+
+    apply_struts(conn, screen, area)
+}
@@ -0,0 +1,110 @@
+//! Golden-image tests of the notification layout.
+//!
+//! Each case is rendered offscreen with `--render-png` and compared against a reference image in
+//! `tests/golden/`. Set `XNOTIFY_BLESS=1` to regenerate the references after an intended change
+//! of the rendering. On failure, the actual rendering and a diff are written next to the test
+//! binary's temporary directory.
+
+use image::{Rgba, RgbaImage};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// A channel may differ by this much before a pixel counts as different, for antialiasing
+const CHANNEL_TOLERANCE: i32 = 48;
+
+// Part of the pixels that may differ, for small differences in font rasterization
+const PIXEL_TOLERANCE: f64 = 0.01;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn render(name: &str, args: &[&str]) -> PathBuf {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let path = out_dir.join(format!("{}.png", name));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .arg("--render-png").arg(&path)
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "xnotify failed: {}", String::from_utf8_lossy(&output.stderr));
+
+    path
+}
+
+fn differs(a: &Rgba<u8>, b: &Rgba<u8>) -> bool {
+    a.data.iter().zip(b.data.iter())
+        .any(|(a, b)| (*a as i32 - *b as i32).abs() > CHANNEL_TOLERANCE)
+}
+
+fn check(name: &str, args: &[&str]) {
+    let actual_path = render(name, args);
+    let reference_path = golden_dir().join(format!("{}.png", name));
+
+    if std::env::var_os("XNOTIFY_BLESS").is_some() {
+        std::fs::copy(&actual_path, &reference_path).unwrap();
+        return;
+    }
+
+    let actual = image::open(&actual_path).unwrap().to_rgba();
+    let reference = image::open(&reference_path)
+        .unwrap_or_else(|e| panic!("{}: {}, run with XNOTIFY_BLESS=1 to create it",
+            reference_path.display(), e))
+        .to_rgba();
+
+    assert_eq!(actual.dimensions(), reference.dimensions(),
+        "{}: size changed, see {}", name, actual_path.display());
+
+    let (width, height) = actual.dimensions();
+    let mut diff = RgbaImage::new(width, height);
+    let mut different = 0;
+
+    for (x, y, pixel) in actual.enumerate_pixels() {
+        let expected = reference.get_pixel(x, y);
+        if differs(pixel, expected) {
+            different += 1;
+            diff.put_pixel(x, y, Rgba { data: [255, 0, 0, 255] });
+        } else {
+            let dim = |c: u8| c / 4;
+            diff.put_pixel(x, y, Rgba { data: [dim(pixel.data[0]), dim(pixel.data[1]),
+                dim(pixel.data[2]), 255] });
+        }
+    }
+
+    let ratio = different as f64 / (width * height) as f64;
+    if ratio > PIXEL_TOLERANCE {
+        let diff_path = actual_path.with_extension("diff.png");
+        diff.save(&diff_path).unwrap();
+        panic!("{}: {} pixels differ from {}, see {} and {}", name, different,
+            reference_path.display(), actual_path.display(), diff_path.display());
+    }
+}
+
+macro_rules! golden {
+    ($name:ident, [$($arg:expr),*]) => {
+        #[test]
+        fn $name() {
+            check(stringify!($name), &[$($arg),*]);
+        }
+    };
+}
+
+golden!(single_line, ["-n", "DejaVu Sans 12", "Hello, world"]);
+golden!(multi_line, ["-n", "DejaVu Sans 12", "First line\nSecond, longer line\nThird"]);
+// Emoji that DejaVu Sans draws itself, so the rendering doesn't depend on which emoji font, if
+// any, fontconfig would fall back to
+golden!(emoji, ["-n", "DejaVu Sans 16", "Coffee and lightning: \u{2615}\u{26a1}"]);
+golden!(markup_is_literal, ["-n", "DejaVu Sans 12", "<b>not bold</b> & <i>not italic</i>"]);
+golden!(serif_font, ["-n", "DejaVu Serif 20", "Serif"]);
+golden!(monospace_font, ["-n", "DejaVu Sans Mono 10", "fn main() {}"]);
+golden!(scale_2_border, ["-n", "DejaVu Sans 12", "--scale", "2", "Scaled border"]);
+golden!(scale_3_border, ["-n", "DejaVu Sans 8", "--scale", "3", "Thick"]);
+golden!(countdown, ["-n", "DejaVu Sans 12", "-t", "5", "--show-countdown", "Countdown"]);
+golden!(scrollbar, ["-n", "DejaVu Sans 12", "--max-height", "40", "1\n2\n3\n4\n5\n6\n7\n8"]);
+golden!(tiny_viewport, ["-n", "DejaVu Sans 12", "--max-height", "2", "a\nb"]);
+golden!(rounded_corners, ["-n", "DejaVu Sans 12", "--scale", "2", "--corner-radius", "8", "Rounded"]);
+golden!(no_box_outline, ["-n", "DejaVu Sans Bold 20", "--no-box", "--outline", "2", "black",
+    "OSD"]);
+golden!(shadow, ["-n", "DejaVu Sans 16", "--shadow", "2,3", "#ff0000", "Shadow"]);
@@ -0,0 +1,89 @@
+//! Tests of `xnotify history` against a prepared history file.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const HISTORY: &str = r#"{"text":"Build finished\nin 3 minutes","tag":"ci","urgency":"normal","shown":1760000000,"closed":1760000005,"reason":"timeout","x":10,"y":20,"args":["-t","5","Build finished"]}
+not a record
+{"text":"Disk \"full\"","tag":"","urgency":"critical","shown":1760000100,"closed":1760000200,"reason":"click","x":-5,"y":20,"args":[]}
+{"text":"Backup done","tag":"backup","urgency":"low","shown":1760000200,"closed":1760000210,"reason":"destroy","x":0,"y":0,"args":[]}
+"#;
+
+// A state directory holding the prepared history, distinct for each test
+fn state_dir(name: &str) -> PathBuf {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("history").join(name);
+    std::fs::create_dir_all(dir.join("xnotify")).unwrap();
+    std::fs::write(dir.join("xnotify").join("history.jsonl"), HISTORY).unwrap();
+    dir
+}
+
+fn history(name: &str, args: &[&str]) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .env("XDG_STATE_HOME", state_dir(name))
+        .arg("history")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "xnotify history {:?} failed", args);
+
+    String::from_utf8(output.stdout).unwrap().lines().map(String::from).collect()
+}
+
+#[test]
+fn lists_all_records_oldest_first() {
+    let lines = history("all", &[]);
+
+    assert_eq!(lines, [
+        "  3  2025-10-09 08:53:20  normal   timeout [ci]  Build finished",
+        "  2  2025-10-09 08:55:00  critical click    Disk \"full\"",
+        "  1  2025-10-09 08:56:40  low      destroy [backup]  Backup done",
+    ]);
+}
+
+#[test]
+fn filters_records() {
+    assert_eq!(history("tag", &["--tag", "ci"]).len(), 1);
+    assert_eq!(history("urgency", &["-u", "critical"]).len(), 1);
+    assert_eq!(history("text", &["--text", "^B.*d.ne$"]).len(), 1);
+    assert_eq!(history("text-anywhere", &["--text", "fin"]).len(), 1);
+    assert_eq!(history("text-alternation", &["--text", "full|done"]).len(), 2);
+    assert_eq!(history("text-optional", &["--text", "Disk \"?full"]).len(), 1);
+    assert_eq!(history("count", &["-c", "2"]).len(), 2);
+    assert!(history("since", &["--since", "1h"]).is_empty());
+}
+
+#[test]
+fn rejects_invalid_text_regex() {
+    let output = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .env("XDG_STATE_HOME", state_dir("invalid-regex"))
+        .args(&["history", "--text", "(unclosed"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid regular expression"));
+}
+
+#[test]
+fn rejects_overflowing_age() {
+    let output = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .env("XDG_STATE_HOME", state_dir("overflowing-age"))
+        .args(&["history", "--since", "999999999999999999d"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid age"));
+}
+
+#[test]
+fn warns_about_damaged_lines() {
+    let output = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .env("XDG_STATE_HOME", state_dir("damaged"))
+        .arg("history")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Skipping damaged line 2"));
+}
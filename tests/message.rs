@@ -0,0 +1,59 @@
+//! Tests of taking the message from the command line, rendered offscreen with `--render-png`.
+
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+// Render with `args` given first, as they would be without `--render-png`, unless they end the
+// options with `--`
+fn render(name: &str, args: &[&str]) -> Vec<u8> {
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("message");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(format!("{}.png", name));
+
+    let mut command = Command::new(env!("CARGO_BIN_EXE_xnotify"));
+    if args.contains(&"--") {
+        command.arg("--render-png").arg(&path).args(args);
+    } else {
+        command.args(args).arg("--render-png").arg(&path);
+    }
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "xnotify {:?} failed: {}", args,
+        String::from_utf8_lossy(&output.stderr));
+
+    std::fs::read(&path).unwrap()
+}
+
+#[test]
+fn words_are_joined_by_spaces() {
+    assert_eq!(render("words", &["hello", "world"]), render("joined", &["hello world"]));
+}
+
+#[test]
+fn words_like_subcommands_are_messages() {
+    for (name, words) in &[
+        ("hi", &["hi"][..]),
+        ("his", &["his"]),
+        ("dns", &["dns", "down"]),
+        ("history-later", &["show", "history"]),
+        ("dashes", &["--", "hi"]),
+        ("history-after-dashes", &["--", "history"]),
+    ] {
+        render(name, words);
+    }
+}
+
+#[test]
+fn words_conflict_with_piped_input() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .args(&["--render-png", "/dev/null", "hello"])
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    drop(child.stdin.take());
+    let output = child.wait_with_output().unwrap();
+
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .contains("both as arguments and on standard input"));
+}
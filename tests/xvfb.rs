@@ -0,0 +1,359 @@
+//! End-to-end tests of the notification window against a private Xvfb server.
+//!
+//! Pointer input is injected through the XTEST extension. The tests need `Xvfb`, so they are
+//! ignored by default; run them with `cargo test -- --ignored`.
+
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{Duration, Instant};
+
+const SCREEN: (u16, u16) = (1024, 768);
+
+static NEXT_DISPLAY: AtomicU32 = AtomicU32::new(0);
+
+fn wait_for<T>(timeout: Duration, mut f: impl FnMut() -> Option<T>) -> Option<T> {
+    let start = Instant::now();
+    while start.elapsed() < timeout {
+        if let Some(value) = f() {
+            return Some(value);
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+    None
+}
+
+struct Xvfb {
+    server: Child,
+    display: String,
+}
+
+impl Xvfb {
+    fn start() -> Self {
+        // Find a display that is not in use, distinct for each test of this process
+        let (number, socket) = loop {
+            let number = 100 + std::process::id() % 1000 * 8
+                + NEXT_DISPLAY.fetch_add(1, Ordering::SeqCst);
+            let socket = format!("/tmp/.X11-unix/X{}", number);
+            if !Path::new(&socket).exists() {
+                break (number, socket);
+            }
+        };
+
+        let server = Command::new("Xvfb")
+            .arg(format!(":{}", number))
+            .args(&["-screen", "0", &format!("{}x{}x24", SCREEN.0, SCREEN.1)])
+            .args(&["+extension", "RANDR", "-nolisten", "tcp"])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("Xvfb is not installed");
+
+        let xvfb = Xvfb { server, display: format!(":{}", number) };
+        wait_for(Duration::from_secs(10), || if Path::new(&socket).exists() { Some(()) } else { None })
+            .expect("Xvfb did not start");
+
+        xvfb
+    }
+
+    fn xnotify(&self, args: &[&str]) -> Child {
+        Command::new(env!("CARGO_BIN_EXE_xnotify"))
+            .env("DISPLAY", &self.display)
+            .env("XDG_STATE_HOME", Path::new(env!("CARGO_TARGET_TMPDIR")).join("state"))
+            .args(&["--scale", "1", "-n", "DejaVu Sans 12"])
+            .args(args)
+            .stdin(Stdio::null())
+            .spawn()
+            .unwrap()
+    }
+
+    // Inject a pointer event through XTEST, and wait until the server has processed it
+    fn fake_input(&self, event: u8, detail: u8, (x, y): (i16, i16)) {
+        let conn = self.connect();
+        let root = conn.get_setup().roots().next().unwrap().root();
+        xcb::test::fake_input(&conn, event, detail, xcb::CURRENT_TIME, root, x, y, 0)
+            .request_check().unwrap();
+        xcb::get_input_focus(&conn).get_reply().unwrap();
+    }
+
+    fn move_pointer(&self, x: i16, y: i16) {
+        self.fake_input(xcb::MOTION_NOTIFY, 0, (x, y));
+    }
+
+    fn press(&self, button: u8) {
+        self.fake_input(xcb::BUTTON_PRESS, button, (0, 0));
+    }
+
+    fn release(&self, button: u8) {
+        self.fake_input(xcb::BUTTON_RELEASE, button, (0, 0));
+    }
+
+    fn connect(&self) -> xcb::Connection {
+        xcb::Connection::connect(Some(&self.display)).unwrap().0
+    }
+
+    // Geometry of the first mapped top-level window, which is the notification
+    fn find_window(&self, conn: &xcb::Connection) -> Option<(i16, i16, u16, u16)> {
+        let root = conn.get_setup().roots().next().unwrap().root();
+        let tree = xcb::query_tree(conn, root).get_reply().ok()?;
+
+        tree.children().iter().find_map(|child| {
+            let attrs = xcb::get_window_attributes(conn, *child).get_reply().ok()?;
+            if attrs.map_state() != xcb::MAP_STATE_VIEWABLE as u8 {
+                return None;
+            }
+            let geometry = xcb::get_geometry(conn, *child).get_reply().ok()?;
+            Some((geometry.x(), geometry.y(), geometry.width(), geometry.height()))
+        })
+    }
+
+    fn wait_for_window(&self, conn: &xcb::Connection) -> (i16, i16, u16, u16) {
+        wait_for(Duration::from_secs(10), || self.find_window(conn))
+            .expect("notification window did not appear")
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+fn exits_within(child: &mut Child, timeout: Duration) -> bool {
+    wait_for(timeout, || child.try_wait().unwrap()).is_some()
+}
+
+fn kill(mut child: Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// Where `--position` should put a window of `size`, centered and clamped to the screen
+fn expected_position(spec: &str, size: u16, screen: u16) -> i16 {
+    let pos = match spec.strip_prefix('%') {
+        Some(percent) => (screen as u64 * percent.parse::<u64>().unwrap() / 100) as i32,
+        None => spec.parse::<i32>().unwrap(),
+    } - size as i32 / 2;
+
+    std::cmp::max(0, std::cmp::min((screen - size) as i32, pos)) as i16
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn window_follows_position() {
+    let xvfb = Xvfb::start();
+    let conn = xvfb.connect();
+
+    for position in &["%50,%50", "%0,%0", "%100,%100", "300,200", "%25,%75"] {
+        let child = xvfb.xnotify(&["-p", position, "Position test"]);
+        let (x, y, width, height) = xvfb.wait_for_window(&conn);
+        kill(child);
+
+        let (px, py) = position.split_once(',').unwrap();
+        assert_eq!((x, y), (expected_position(px, width, SCREEN.0),
+            expected_position(py, height, SCREEN.1)), "position {}", position);
+
+        wait_for(Duration::from_secs(5), || match xvfb.find_window(&conn) {
+            Some(_) => None,
+            None => Some(()),
+        });
+    }
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn timeout_closes_window() {
+    let xvfb = Xvfb::start();
+
+    // Keep the pointer away, as hovering pauses the timeout
+    xvfb.move_pointer(1000, 700);
+
+    let mut child = xvfb.xnotify(&["-p", "%0,%0", "-t", "0.5", "Timeout test"]);
+    xvfb.wait_for_window(&xvfb.connect());
+    assert!(exits_within(&mut child, Duration::from_secs(5)), "timeout did not close the window");
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn hover_pauses_timeout() {
+    let xvfb = Xvfb::start();
+
+    let mut child = xvfb.xnotify(&["-p", "%50,%50", "-t", "0.5", "--hover-grace", "1",
+        "Hover test"]);
+    let (x, y, width, height) = xvfb.wait_for_window(&xvfb.connect());
+
+    xvfb.move_pointer(x + width as i16 / 2, y + height as i16 / 2);
+    assert!(!exits_within(&mut child, Duration::from_secs(2)), "hovering did not pause");
+
+    // After leaving, the grace is left of the timeout
+    xvfb.move_pointer(1000, 700);
+    assert!(!exits_within(&mut child, Duration::from_millis(600)), "the grace was not left");
+    assert!(exits_within(&mut child, Duration::from_secs(5)), "leaving did not resume");
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn right_click_dismisses() {
+    let xvfb = Xvfb::start();
+
+    let mut child = xvfb.xnotify(&["-p", "%50,%50", "Right click test"]);
+    let (x, y, width, height) = xvfb.wait_for_window(&xvfb.connect());
+
+    xvfb.move_pointer(x + width as i16 / 2, y + height as i16 / 2);
+    xvfb.press(3);
+    xvfb.release(3);
+
+    assert!(exits_within(&mut child, Duration::from_secs(5)), "right click did not dismiss");
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn drag_moves_window_and_cancels_timeout() {
+    let xvfb = Xvfb::start();
+    let conn = xvfb.connect();
+
+    xvfb.move_pointer(1000, 700);
+    let mut child = xvfb.xnotify(&["-p", "%50,%50", "-t", "1.5", "--snap-distance", "0",
+        "Drag test"]);
+    let (x, y, width, height) = xvfb.wait_for_window(&conn);
+
+    let (cx, cy) = (x + width as i16 / 2, y + height as i16 / 2);
+    xvfb.move_pointer(cx, cy);
+    xvfb.press(1);
+    xvfb.move_pointer(cx - 100, cy - 50);
+    xvfb.release(1);
+
+    let moved = wait_for(Duration::from_secs(5), || match xvfb.find_window(&conn) {
+        Some((nx, ny, _, _)) if (nx, ny) == (x - 100, y - 50) => Some(()),
+        _ => None,
+    });
+    assert!(moved.is_some(), "drag did not move the window");
+
+    // Leave the window, so that only the drag keeps it on the screen
+    xvfb.move_pointer(1000, 700);
+    assert!(!exits_within(&mut child, Duration::from_secs(3)), "drag did not cancel the timeout");
+
+    kill(child);
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn rounded_corners_shape_window() {
+    let xvfb = Xvfb::start();
+    let conn = xvfb.connect();
+
+    let child = xvfb.xnotify(&["-p", "%50,%50", "--corner-radius", "10", "Shape test"]);
+    let (_, _, width, height) = xvfb.wait_for_window(&conn);
+
+    let root = conn.get_setup().roots().next().unwrap().root();
+    let win = *xcb::query_tree(&conn, root).get_reply().unwrap().children().last().unwrap();
+    let shape = xcb::shape::get_rectangles(&conn, win, xcb::shape::SK_BOUNDING as u8)
+        .get_reply().unwrap();
+    kill(child);
+
+    let inside = |x: i16, y: i16| shape.rectangles().any(|r| {
+        x >= r.x() && y >= r.y() && x < r.x() + r.width() as i16 && y < r.y() + r.height() as i16
+    });
+    assert!(!inside(0, 0), "top left corner is not cut away");
+    assert!(!inside(width as i16 - 1, height as i16 - 1), "bottom right corner is not cut away");
+    assert!(inside(width as i16 / 2, 0), "top edge is cut away");
+    assert!(inside(0, height as i16 / 2), "left edge is cut away");
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn no_box_shapes_window_to_text() {
+    let xvfb = Xvfb::start();
+    let conn = xvfb.connect();
+
+    let child = xvfb.xnotify(&["-p", "%50,%50", "--no-box", "--outline", "2", "black", "I"]);
+    let (_, _, width, height) = xvfb.wait_for_window(&conn);
+
+    let root = conn.get_setup().roots().next().unwrap().root();
+    let win = *xcb::query_tree(&conn, root).get_reply().unwrap().children().last().unwrap();
+    let shape = xcb::shape::get_rectangles(&conn, win, xcb::shape::SK_BOUNDING as u8)
+        .get_reply().unwrap();
+    kill(child);
+
+    // Only the glyph and its outline remain of the window
+    let area: u32 = shape.rectangles().map(|r| r.width() as u32 * r.height() as u32).sum();
+    assert!(area > 0, "the text is cut away");
+    assert!(area < width as u32 * height as u32 / 2, "the window is not shaped to the text");
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn dnd_queues_until_turned_off() {
+    let xvfb = Xvfb::start();
+    let conn = xvfb.connect();
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+            .env("DISPLAY", &xvfb.display)
+            .env("XDG_STATE_HOME", Path::new(env!("CARGO_TARGET_TMPDIR")).join("dnd"))
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "xnotify {:?} failed", args);
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    run(&["dnd", "on"]);
+    assert!(run(&["dnd", "status"]).starts_with("on"));
+
+    // Low urgency is suppressed and normal queued, both without a window
+    run(&["-u", "low", "Suppressed"]);
+    run(&["-p", "%50,%50", "Queued"]);
+    assert!(xvfb.find_window(&conn).is_none(), "a window appeared while do not disturb is on");
+    assert!(run(&["dnd", "status"]).contains("1 queued"));
+
+    // Critical notifications bypass it
+    let child = xvfb.xnotify(&["-u", "critical", "Critical"]);
+    xvfb.wait_for_window(&conn);
+    kill(child);
+    wait_for(Duration::from_secs(5), || match xvfb.find_window(&conn) {
+        Some(_) => None,
+        None => Some(()),
+    });
+
+    // Turning it off returns at once, leaving the queued notification on the screen
+    let mut off = Command::new(env!("CARGO_BIN_EXE_xnotify"))
+        .env("DISPLAY", &xvfb.display)
+        .env("XDG_STATE_HOME", Path::new(env!("CARGO_TARGET_TMPDIR")).join("dnd"))
+        .args(&["dnd", "off"])
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    assert!(exits_within(&mut off, Duration::from_secs(5)), "dnd off waited for the notification");
+    xvfb.wait_for_window(&conn);
+    assert_eq!(run(&["dnd", "status"]), "off\n");
+}
+
+#[test]
+#[ignore = "needs Xvfb"]
+fn dedupe_counts_repeats_on_existing_window() {
+    let xvfb = Xvfb::start();
+    let conn = xvfb.connect();
+
+    let child = xvfb.xnotify(&["-p", "0,0", "--dedupe", "60", "Flapping"]);
+    let (_, _, width, _) = xvfb.wait_for_window(&conn);
+
+    // The repeat exits at once, and the counter widens the existing window
+    let mut repeat = xvfb.xnotify(&["-p", "0,0", "--dedupe", "60", "Flapping"]);
+    assert!(exits_within(&mut repeat, Duration::from_secs(5)), "the repeat showed a window");
+
+    let grown = wait_for(Duration::from_secs(5), || match xvfb.find_window(&conn) {
+        Some((_, _, new_width, _)) if new_width > width => Some(()),
+        _ => None,
+    });
+    assert!(grown.is_some(), "the repeat was not counted");
+
+    let root = conn.get_setup().roots().next().unwrap().root();
+    let tree = xcb::query_tree(&conn, root).get_reply().unwrap();
+    let mapped = tree.children().iter()
+        .filter(|w| xcb::get_window_attributes(&conn, **w).get_reply()
+            .map(|a| a.map_state() == xcb::MAP_STATE_VIEWABLE as u8).unwrap_or(false))
+        .count();
+    kill(child);
+    assert_eq!(mapped, 1, "the repeat showed another window");
+}